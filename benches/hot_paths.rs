@@ -0,0 +1,100 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lru::{LruCache, SmallKey};
+
+const SIZE: u64 = 10_000;
+
+fn full_cache() -> LruCache<u64, u64> {
+    let mut cache = LruCache::new(SIZE as usize);
+    for key in 0..SIZE {
+        cache.put(key, key);
+    }
+    cache
+}
+
+// each iteration works on a different key, so promotions actually relink the list
+fn hot_paths(c: &mut Criterion) {
+    let mut cache = full_cache();
+    let mut key = 0;
+    c.bench_function("get hit", |b| b.iter(|| {
+        key = (key + 7) % SIZE;
+        black_box(cache.get(&key))
+    }));
+
+    let mut cache = full_cache();
+    let mut missing = SIZE;
+    c.bench_function("get miss", |b| b.iter(|| {
+        missing += 1;
+        black_box(cache.get(&missing))
+    }));
+
+    let mut cache = full_cache();
+    c.bench_function("put update", |b| b.iter(|| {
+        key = (key + 7) % SIZE;
+        cache.put(key, black_box(key));
+    }));
+
+    let mut cache = full_cache();
+    let mut next = SIZE;
+    c.bench_function("put evict", |b| b.iter(|| {
+        next += 1;
+        cache.put(next, black_box(next));
+    }));
+
+    let mut cache = full_cache();
+    c.bench_function("remove and put back", |b| b.iter(|| {
+        key = (key + 7) % SIZE;
+        let value = cache.remove(&key);
+        cache.put(key, black_box(value.unwrap_or(key)));
+    }));
+}
+
+// short string keys get cloned into the map and the node on every write
+fn string_keys(c: &mut Criterion) {
+    let keys: Vec<String> = (0..SIZE).map(|key| format!("user:{}", key)).collect();
+    let mut strings = LruCache::new(SIZE as usize / 2);
+    let mut next = 0;
+    c.bench_function("put evict, String keys", |b| b.iter(|| {
+        next = (next + 1) % keys.len();
+        strings.put(keys[next].clone(), black_box(next));
+    }));
+
+    let mut small = LruCache::new(SIZE as usize / 2);
+    c.bench_function("put evict, SmallKey keys", |b| b.iter(|| {
+        next = (next + 1) % keys.len();
+        small.put(SmallKey::from(&keys[next]), black_box(next));
+    }));
+}
+
+// the same hot paths against the pointer-linked cache
+#[cfg(feature = "raw")]
+fn raw_hot_paths(c: &mut Criterion) {
+    use lru::RawLruCache;
+
+    let full_raw_cache = || {
+        let mut cache = RawLruCache::new(SIZE as usize);
+        for key in 0..SIZE {
+            cache.put(key, key);
+        }
+        cache
+    };
+
+    let mut cache = full_raw_cache();
+    let mut key = 0;
+    c.bench_function("raw get hit", |b| b.iter(|| {
+        key = (key + 7) % SIZE;
+        black_box(cache.get(&key))
+    }));
+
+    let mut cache = full_raw_cache();
+    let mut next = SIZE;
+    c.bench_function("raw put evict", |b| b.iter(|| {
+        next += 1;
+        cache.put(next, black_box(next));
+    }));
+}
+
+#[cfg(not(feature = "raw"))]
+fn raw_hot_paths(_: &mut Criterion) {}
+
+criterion_group!(benches, hot_paths, string_keys, raw_hot_paths);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::thread;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use lru::workload::{self, Op, Zipfian};
+use lru::{ConcurrentLruCache, LruCache};
+
+const KEYS: u64 = 100_000;
+const OPS: usize = 10_000;
+
+// a read mostly zipfian workload against caches holding 1% and 10% of the keys
+fn mixed_zipfian(c: &mut Criterion) {
+    let ops: Vec<Op> = workload::mixed(Zipfian::new(KEYS, 1.0, 1), 0.9, 2).take(OPS).collect();
+    let mut group = c.benchmark_group("mixed zipfian");
+    group.throughput(Throughput::Elements(OPS as u64));
+    for capacity in [KEYS / 100, KEYS / 10] {
+        group.bench_with_input(BenchmarkId::from_parameter(capacity), &capacity, |b, &capacity| {
+            let mut cache = LruCache::new(capacity as usize);
+            b.iter(|| {
+                for op in &ops {
+                    match *op {
+                        Op::Get(key) => if cache.get(&key).is_none() {
+                            cache.put(key, key);
+                        },
+                        Op::Put(key) => {
+                            cache.put(key, black_box(key));
+                        }
+                    }
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+// threads hammering one concurrent cache, few shards make them fight over the locks
+fn concurrent_contention(c: &mut Criterion) {
+    let threads = thread::available_parallelism().map_or(4, |cores| cores.get()).min(8);
+    let ops: Arc<Vec<Op>> = Arc::new(workload::mixed(Zipfian::new(KEYS, 1.0, 3), 0.9, 4).take(OPS).collect());
+    let mut group = c.benchmark_group("concurrent contention");
+    group.throughput(Throughput::Elements((OPS * threads) as u64));
+    for shards in [1, 16] {
+        let cache = Arc::new(ConcurrentLruCache::new(KEYS as usize / 10, shards));
+        group.bench_with_input(BenchmarkId::new("shards", shards), &shards, |b, _| b.iter(|| {
+            let workers: Vec<_> = (0..threads).map(|worker| {
+                let (cache, ops) = (Arc::clone(&cache), Arc::clone(&ops));
+                thread::spawn(move || {
+                    // each thread starts at a different point of the trace
+                    for op in ops.iter().cycle().skip(worker * OPS / threads).take(OPS) {
+                        match *op {
+                            Op::Get(key) => if cache.get(&key).is_none() {
+                                cache.put(key, key);
+                            },
+                            Op::Put(key) => {
+                                cache.put(key, key);
+                            }
+                        }
+                    }
+                })
+            }).collect();
+            for worker in workers {
+                worker.join().unwrap();
+            }
+        }));
+    }
+    group.finish();
+}
+
+// threads only reading a warm cache: with buffered_recency hits share the shard's read lock instead of queueing
+// on its write lock
+fn concurrent_reads(c: &mut Criterion) {
+    let threads = thread::available_parallelism().map_or(4, |cores| cores.get()).min(8);
+    let keys: Arc<Vec<u64>> = Arc::new(Zipfian::new(KEYS / 10, 1.0, 5).take(OPS).collect());
+    let mut group = c.benchmark_group("concurrent reads");
+    group.throughput(Throughput::Elements((OPS * threads) as u64));
+    for buffered in [false, true] {
+        let mut cache = ConcurrentLruCache::new(KEYS as usize / 10, 4);
+        if buffered {
+            cache = cache.buffered_recency(256);
+        }
+        for key in 0..KEYS / 10 {
+            cache.put(key, key);
+        }
+        let cache = Arc::new(cache);
+        let name = if buffered { "buffered" } else { "locked" };
+        group.bench_function(name, |b| b.iter(|| {
+            let workers: Vec<_> = (0..threads).map(|worker| {
+                let (cache, keys) = (Arc::clone(&cache), Arc::clone(&keys));
+                thread::spawn(move || {
+                    for key in keys.iter().cycle().skip(worker * OPS / threads).take(OPS) {
+                        black_box(cache.get(key));
+                    }
+                })
+            }).collect();
+            for worker in workers {
+                worker.join().unwrap();
+            }
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, mixed_zipfian, concurrent_contention, concurrent_reads);
+criterion_main!(benches);
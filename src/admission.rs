@@ -0,0 +1,125 @@
+use std::hash::{Hash, Hasher};
+
+use crate::FrequencySketch;
+use crate::bloom::BloomFilter;
+
+// a key as far as it can be hashed. lookups may come in by any borrowed form of K, which hashes like K itself,
+// so policies counting requests see those through this rather than as a K
+pub trait HashKey {
+    fn hash_key(&self, state: &mut dyn Hasher);
+}
+
+impl<T: Hash> HashKey for T {
+    fn hash_key(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state);
+    }
+}
+
+impl Hash for dyn HashKey + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_key(state);
+    }
+}
+
+// decides whether a new key may evict the least recently used one when the cache is full,
+// so one-off entries can be kept from pushing out proven hot ones
+pub trait AdmissionPolicy<K> {
+    // called for every lookup and insert, hit or miss
+    fn record(&mut self, _key: &dyn HashKey) {}
+
+    fn admit(&mut self, candidate: &K, victim: &K) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlwaysAdmit;
+
+impl<K> AdmissionPolicy<K> for AlwaysAdmit {
+    fn admit(&mut self, _candidate: &K, _victim: &K) -> bool {
+        true
+    }
+}
+
+// tinylfu: a new key only replaces the victim if it has been seen more often recently
+pub struct TinyLfu {
+    sketch: FrequencySketch,
+    doorkeeper: Option<BloomFilter>
+}
+
+impl TinyLfu {
+    pub fn new(capacity: usize) -> Self {
+        TinyLfu { sketch: FrequencySketch::new(capacity), doorkeeper: None }
+    }
+
+    // a key's first sighting only goes into a bloom filter, so one-hit wonders never reach the sketch.
+    // the filter is cleared whenever the sketch halves
+    pub fn doorkeeper(mut self, capacity: usize) -> Self {
+        self.doorkeeper = Some(BloomFilter::new(capacity, 0.01));
+        self
+    }
+
+    fn estimate<K: Hash>(&self, key: &K) -> u8 {
+        let seen = self.doorkeeper.as_ref().is_some_and(|doorkeeper| doorkeeper.contains(key));
+        self.sketch.estimate(key).saturating_add(seen as u8)
+    }
+
+    pub fn sketch(&self) -> &FrequencySketch {
+        &self.sketch
+    }
+}
+
+impl<K: Hash> AdmissionPolicy<K> for TinyLfu {
+    fn record(&mut self, key: &dyn HashKey) {
+        if let Some(doorkeeper) = &mut self.doorkeeper {
+            if !doorkeeper.contains(key) {
+                doorkeeper.insert(key);
+                return;
+            }
+        }
+
+        let samples = self.sketch.samples();
+        self.sketch.record(key);
+        if self.sketch.samples() < samples {
+            if let Some(doorkeeper) = &mut self.doorkeeper {
+                doorkeeper.clear();
+            }
+        }
+    }
+
+    fn admit(&mut self, candidate: &K, victim: &K) -> bool {
+        self.estimate(candidate) > self.estimate(victim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use super::*;
+
+    #[test]
+    fn test_tiny_lfu_keeps_hot_keys() {
+        let mut lru = LruCache::new(2).admission(TinyLfu::new(16));
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.get(&1);
+        lru.get(&2);
+        lru.put(3, 3);
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get(&3), None);
+
+        lru.get(&3);
+        lru.get(&3);
+        lru.put(3, 3);
+        assert_eq!(lru.get(&3), Some(3));
+    }
+
+    #[test]
+    fn test_tiny_lfu_doorkeeper() {
+        let mut policy = TinyLfu::new(16).doorkeeper(16);
+        AdmissionPolicy::<i32>::record(&mut policy, &1);
+        assert_eq!(policy.sketch().estimate(&1), 0);
+        assert!(!policy.admit(&2, &1));
+        AdmissionPolicy::<i32>::record(&mut policy, &1);
+        assert_eq!(policy.sketch().estimate(&1), 1);
+        assert!(policy.admit(&1, &2));
+    }
+}
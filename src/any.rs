@@ -0,0 +1,150 @@
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::{InsertOutcome, LruCache};
+
+type AnyValue = Arc<dyn Any + Send + Sync>;
+
+// a (TypeId, key) pair seen through a borrowed form of the key, so lookups by e.g. &str into String keys find
+// the stored (TypeId, String) without building one. hashes and compares like the tuple itself
+// the stored key type K only ties the borrowed form back to an owned (TypeId, K), e.g. for heavy hitters
+trait TypedKey<K, Q: ?Sized> {
+    fn parts(&self) -> (TypeId, &Q);
+}
+
+impl<B: Borrow<Q>, K, Q: ?Sized> TypedKey<K, Q> for (TypeId, B) {
+    fn parts(&self) -> (TypeId, &Q) {
+        (self.0, self.1.borrow())
+    }
+}
+
+impl<'a, K: Borrow<Q> + 'a, Q: ?Sized + 'a> Borrow<dyn TypedKey<K, Q> + 'a> for (TypeId, K) {
+    fn borrow(&self) -> &(dyn TypedKey<K, Q> + 'a) {
+        self
+    }
+}
+
+impl<'a, K: Borrow<Q> + 'a, Q: ?Sized + ToOwned<Owned = K> + 'a> ToOwned for dyn TypedKey<K, Q> + 'a {
+    type Owned = (TypeId, K);
+
+    fn to_owned(&self) -> (TypeId, K) {
+        let (type_id, key) = self.parts();
+        (type_id, key.to_owned())
+    }
+}
+
+impl<K, Q: ?Sized + Hash> Hash for dyn TypedKey<K, Q> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.parts().hash(state);
+    }
+}
+
+impl<K, Q: ?Sized + Eq> PartialEq for dyn TypedKey<K, Q> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.parts() == other.parts()
+    }
+}
+
+impl<K, Q: ?Sized + Eq> Eq for dyn TypedKey<K, Q> + '_ {}
+
+// one lru cache for values of mixed types, e.g. everything a request or session wants to memoize.
+// entries are addressed by key and value type, so the same key can hold one value of each type,
+// and all of them share the capacity. hits hand out an Arc, the value is never cloned
+pub struct AnyLruCache<K: Clone + Eq + Hash> {
+    cache: LruCache<(TypeId, K), AnyValue>
+}
+
+impl<K: Clone + Eq + Hash> AnyLruCache<K> {
+    pub fn new(capacity: usize) -> Self {
+        AnyLruCache { cache: LruCache::new(capacity) }
+    }
+
+    pub fn get<T: Any + Send + Sync, Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<Arc<T>>
+    where
+        K: Borrow<Q>
+    {
+        let value = self.cache.get::<dyn TypedKey<K, Q>>(&(TypeId::of::<T>(), key))?;
+        value.downcast().ok()
+    }
+
+    // reads a value without changing its recency
+    pub fn peek<T: Any + Send + Sync, Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<Arc<T>>
+    where
+        K: Borrow<Q>
+    {
+        let value = self.cache.peek::<dyn TypedKey<K, Q>>(&(TypeId::of::<T>(), key))?;
+        value.downcast().ok()
+    }
+
+    pub fn contains_key<T: Any + Send + Sync, Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.cache.contains_key::<dyn TypedKey<K, Q>>(&(TypeId::of::<T>(), key))
+    }
+
+    pub fn put<T: Any + Send + Sync>(&mut self, key: K, value: T) -> InsertOutcome {
+        self.cache.put((TypeId::of::<T>(), key), Arc::new(value))
+    }
+
+    pub fn delete<T: Any + Send + Sync, Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove::<T, Q>(key);
+    }
+
+    pub fn remove<T: Any + Send + Sync, Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<Arc<T>>
+    where
+        K: Borrow<Q>
+    {
+        let value = self.cache.remove::<dyn TypedKey<K, Q>>(&(TypeId::of::<T>(), key))?;
+        value.downcast().ok()
+    }
+
+    // entries of every type
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        name: String
+    }
+
+    #[test]
+    fn test_any_cache_keys_by_type() {
+        let mut cache = AnyLruCache::new(2);
+        assert_eq!(cache.put(1, User { name: "ada".to_string() }), InsertOutcome::Inserted);
+        assert_eq!(cache.put(1, 42u64), InsertOutcome::Inserted);
+        assert_eq!(cache.get::<User, _>(&1).unwrap().name, "ada");
+        assert_eq!(cache.get::<u64, _>(&1).as_deref(), Some(&42));
+        assert_eq!(cache.get::<u32, _>(&1), None);
+        assert_eq!(cache.len(), 2);
+
+        // the user is least recently used
+        cache.put(2, "two");
+        assert!(!cache.contains_key::<User, _>(&1));
+        assert_eq!(cache.remove::<u64, _>(&1).as_deref(), Some(&42));
+        assert_eq!(cache.peek::<&str, _>(&2).as_deref(), Some(&"two"));
+    }
+}
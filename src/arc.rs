@@ -0,0 +1,34 @@
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::LruCache;
+
+// hits hand out a refcount bump instead of cloning the value,
+// and evicted values stay alive for as long as someone still holds them
+pub type ArcCache<K, V> = LruCache<K, Arc<V>>;
+
+impl<K: Clone + Eq + Hash, V> LruCache<K, Arc<V>> {
+    pub fn put_shared(&mut self, key: K, value: V) -> Arc<V> {
+        let value = Arc::new(value);
+        self.put(key, Arc::clone(&value));
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Blob(Vec<u8>);
+
+    #[test]
+    fn test_arc_cache_shares_values() {
+        let mut cache: ArcCache<i32, Blob> = ArcCache::new(1);
+        let blob = cache.put_shared(1, Blob(vec![1, 2, 3]));
+        let hit = cache.get(&1).unwrap();
+        assert!(Arc::ptr_eq(&blob, &hit));
+        cache.put_shared(2, Blob(vec![4]));
+        assert_eq!(cache.get(&1).map(|_| ()), None);
+        assert_eq!(hit.0, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use crate::runtime::{BoxFuture, Runtime};
+use crate::LruCache;
+use crate::loading::RefreshGuard;
+
+// entries copied out of the cache at the moment the stream was created
+pub struct EntriesStream<K, V> {
+    entries: std::vec::IntoIter<(K, V)>
+}
+
+impl<K, V> Stream for EntriesStream<K, V> {
+    type Item = (K, V);
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.entries.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> Unpin for EntriesStream<K, V> {}
+
+type AsyncLoader<K, V> = Arc<dyn Fn(K) -> BoxFuture<Option<V>> + Send + Sync>;
+
+// a loading cache for async code, timers and background refreshes run on the given runtime.
+// the inner lock is never held across an await
+pub struct AsyncLoadingCache<K: Clone + Eq + Hash, V, R> {
+    cache: Arc<Mutex<LruCache<K, V>>>,
+    loader: AsyncLoader<K, V>,
+    runtime: R,
+    refresh_ahead: Option<f64>,
+    refreshing: Arc<Mutex<HashSet<K>>>
+}
+
+impl<K, V, R> AsyncLoadingCache<K, V, R>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static,
+    R: Runtime
+{
+    pub fn new<F, Fut>(cache: LruCache<K, V>, runtime: R, loader: F) -> Self
+    where
+        F: Fn(K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<V>> + Send + 'static
+    {
+        AsyncLoadingCache {
+            cache: Arc::new(Mutex::new(cache)),
+            loader: Arc::new(move |key| Box::pin(loader(key))),
+            runtime,
+            refresh_ahead: None,
+            refreshing: Arc::new(Mutex::new(HashSet::new()))
+        }
+    }
+
+    // once a hit is past this fraction of its ttl, reload it on a background task
+    pub fn refresh_ahead(mut self, fraction: f64) -> Self {
+        self.refresh_ahead = Some(fraction);
+        self
+    }
+
+    pub async fn get(&self, key: &K) -> Option<V> {
+        let (value, progress) = {
+            let mut cache = self.cache.lock().unwrap();
+            let value = cache.get(key);
+            (value, cache.ttl_progress(key))
+        };
+
+        match value {
+            Some(value) => {
+                if let (Some(fraction), Some(progress)) = (self.refresh_ahead, progress) {
+                    if progress >= fraction {
+                        self.refresh(key.clone());
+                    }
+                }
+                Some(value)
+            },
+            None => {
+                let value = (self.loader)(key.clone()).await?;
+                self.cache.lock().unwrap().put(key.clone(), value.clone());
+                Some(value)
+            }
+        }
+    }
+
+    // serves hits from the cache and fetches all the misses with a single call to `batch_loader`,
+    // keys the loader leaves out of its result are missing from the returned map
+    pub async fn get_many_async<F, Fut>(&self, keys: Vec<K>, batch_loader: F) -> HashMap<K, V>
+    where
+        F: FnOnce(Vec<K>) -> Fut,
+        Fut: Future<Output = HashMap<K, V>>
+    {
+        let mut found = HashMap::with_capacity(keys.len());
+        let mut misses = Vec::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for key in keys {
+                if found.contains_key(&key) || misses.contains(&key) {
+                    continue;
+                }
+                match cache.get(&key) {
+                    Some(value) => {
+                        found.insert(key, value);
+                    },
+                    None => misses.push(key)
+                }
+            }
+        }
+
+        if misses.is_empty() {
+            return found;
+        }
+
+        let loaded = batch_loader(misses).await;
+        let mut cache = self.cache.lock().unwrap();
+        for (key, value) in loaded {
+            cache.put(key.clone(), value.clone());
+            found.insert(key, value);
+        }
+        found
+    }
+
+    pub fn put(&self, key: K, value: V) {
+        self.cache.lock().unwrap().put(key, value);
+    }
+
+    pub fn delete(&self, key: &K) {
+        self.cache.lock().unwrap().delete(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.lock().unwrap().is_empty()
+    }
+
+    // a snapshot of the live entries, least recently used first
+    pub fn entries_stream(&self) -> EntriesStream<K, V> {
+        let entries = self.cache.lock().unwrap().entries();
+        EntriesStream { entries: entries.into_iter() }
+    }
+
+    // purges expired entries every `interval` until the cache is dropped
+    pub fn sweep_every(&self, interval: Duration) {
+        let cache: Weak<Mutex<LruCache<K, V>>> = Arc::downgrade(&self.cache);
+        let runtime = self.runtime.clone();
+        self.runtime.spawn(Box::pin(async move {
+            loop {
+                runtime.sleep(interval).await;
+                match cache.upgrade() {
+                    None => break,
+                    Some(cache) => cache.lock().unwrap().purge_expired()
+                };
+            }
+        }));
+    }
+
+    fn refresh(&self, key: K) {
+        // the guard is dropped with the task, also when the loader panics
+        let Some(refresh) = RefreshGuard::start(&self.refreshing, key) else { return };
+
+        let cache = Arc::clone(&self.cache);
+        let loader = Arc::clone(&self.loader);
+        self.runtime.spawn(Box::pin(async move {
+            if let Some(value) = loader(refresh.key.clone()).await {
+                cache.lock().unwrap().put(refresh.key.clone(), value);
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park()
+            }
+        }
+    }
+
+    // runs every task on its own thread, good enough for tests
+    #[derive(Clone)]
+    pub(crate) struct ThreadRuntime;
+
+    impl Runtime for ThreadRuntime {
+        fn spawn(&self, task: BoxFuture<()>) {
+            thread::spawn(move || block_on(task));
+        }
+
+        fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+            let done = Arc::new(AtomicBool::new(false));
+            Box::pin(std::future::poll_fn(move |context| {
+                if done.load(Ordering::SeqCst) {
+                    return Poll::Ready(());
+                }
+                let done = Arc::clone(&done);
+                let waker = context.waker().clone();
+                thread::spawn(move || {
+                    thread::sleep(duration);
+                    done.store(true, Ordering::SeqCst);
+                    waker.wake();
+                });
+                Poll::Pending
+            }))
+        }
+    }
+
+    #[test]
+    fn test_async_cache_loads_and_refreshes() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = AsyncLoadingCache::new(LruCache::with_ttl(2, Duration::from_secs(60)), ThreadRuntime, move |key: i32| {
+            let loaded = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Some(key * 10 + loaded as i32) }
+        }).refresh_ahead(0.0);
+
+        assert_eq!(block_on(cache.get(&1)), Some(10));
+        assert_eq!(block_on(cache.get(&1)), Some(10));
+        for _ in 0..100 {
+            if block_on(cache.get(&1)) == Some(11) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_async_cache_get_many() {
+        let cache = AsyncLoadingCache::new(LruCache::new(10), ThreadRuntime, |_: i32| async { None::<i32> });
+        cache.put(1, 10);
+        let batches = AtomicUsize::new(0);
+        let found = block_on(cache.get_many_async(vec![1, 2, 3, 2], |misses| {
+            batches.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(misses, vec![2, 3]);
+            async { HashMap::from([(2, 20)]) }
+        }));
+        assert_eq!(found, HashMap::from([(1, 10), (2, 20)]));
+        assert_eq!(batches.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_async_cache_entries_stream() {
+        let cache = AsyncLoadingCache::new(LruCache::new(10), ThreadRuntime, |_: i32| async { None::<i32> });
+        cache.put(1, 10);
+        cache.put(2, 20);
+        let mut stream = cache.entries_stream();
+        cache.delete(&1);
+        assert_eq!(stream.size_hint(), (2, Some(2)));
+        let mut drained = Vec::new();
+        while let Some(entry) = block_on(std::future::poll_fn(|context| Pin::new(&mut stream).poll_next(context))) {
+            drained.push(entry);
+        }
+        assert_eq!(drained, vec![(1, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn test_async_cache_sweeper() {
+        let cache = AsyncLoadingCache::new(LruCache::new(2), ThreadRuntime, |_: i32| async { None::<i32> });
+        cache.cache.lock().unwrap().put_with_ttl(1, 1, Duration::ZERO);
+        cache.sweep_every(Duration::from_millis(5));
+        for _ in 0..100 {
+            if cache.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(cache.is_empty());
+    }
+}
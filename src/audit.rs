@@ -0,0 +1,85 @@
+#[cfg(feature = "audit")]
+use std::collections::VecDeque;
+#[cfg(feature = "audit")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "audit")]
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "audit")]
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOp {
+    Get,
+    Put,
+    Delete,
+    // dropped to make room
+    Evict,
+    Expire,
+    Invalidate,
+    // moved into another cache by split_off_where
+    Split,
+    Reset
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Hit,
+    Miss,
+    Inserted,
+    Updated,
+    // turned away by the admission policy
+    Rejected,
+    Removed
+}
+
+#[cfg(feature = "audit")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub op: AuditOp,
+    // the key's DefaultHasher hash, None for whole cache operations
+    pub key_hash: Option<u64>,
+    pub at: SystemTime,
+    pub outcome: AuditOutcome
+}
+
+#[cfg(feature = "audit")]
+impl AuditRecord {
+    pub fn is_for<K: Hash + ?Sized>(&self, key: &K) -> bool {
+        self.key_hash == Some(hash_key(key))
+    }
+}
+
+// the most recent `capacity` operations, oldest first
+#[cfg(feature = "audit")]
+pub(crate) struct AuditLog {
+    records: VecDeque<AuditRecord>,
+    capacity: usize
+}
+
+#[cfg(feature = "audit")]
+impl AuditLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        AuditLog { records: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub(crate) fn record<K: Hash + ?Sized>(&mut self, op: AuditOp, key: Option<&K>, outcome: AuditOutcome) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(AuditRecord { op, key_hash: key.map(hash_key), at: SystemTime::now(), outcome });
+    }
+
+    pub(crate) fn records(&self) -> Vec<AuditRecord> {
+        self.records.iter().copied().collect()
+    }
+}
+
+#[cfg(feature = "audit")]
+fn hash_key<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,86 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+// a plain bloom filter, keys can be added but not removed
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+    hasher: RandomState
+}
+
+impl BloomFilter {
+    // sized so that after `items` inserts roughly `false_positive_rate` of lookups for absent keys pass
+    pub(crate) fn new(items: usize, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-items * false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5).ln() / (ln2 * ln2)).ceil().max(64.0);
+        BloomFilter {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes: ((bits / items * ln2).round() as u32).clamp(1, 16),
+            hasher: RandomState::new()
+        }
+    }
+
+    pub(crate) fn insert<K: Hash + ?Sized>(&mut self, key: &K) {
+        for bit in self.bits_for(key) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    pub(crate) fn contains<K: Hash + ?Sized>(&self, key: &K) -> bool {
+        self.bits_for(key).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    // double hashing, every probe is derived from one 64 bit hash. the iterator borrows neither self nor the key
+    fn bits_for<K: Hash + ?Sized>(&self, key: &K) -> impl Iterator<Item = usize> {
+        let hash = self.hasher.hash_one(key);
+        let (first, second) = (hash as u32 as u64, (hash >> 32) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+    }
+}
+
+// a bloom filter over the keys present in a cache. removed keys linger as false positives
+// until as many were removed as the filter holds, then it is rebuilt from the live keys
+pub(crate) struct KeyFilter {
+    filter: BloomFilter,
+    items: usize,
+    false_positive_rate: f64,
+    removed: usize
+}
+
+impl KeyFilter {
+    pub(crate) fn new(items: usize, false_positive_rate: f64) -> Self {
+        KeyFilter {
+            filter: BloomFilter::new(items, false_positive_rate),
+            items,
+            false_positive_rate,
+            removed: 0
+        }
+    }
+
+    pub(crate) fn insert<K: Hash>(&mut self, key: &K) {
+        self.filter.insert(key);
+    }
+
+    pub(crate) fn might_contain<K: Hash + ?Sized>(&self, key: &K) -> bool {
+        self.filter.contains(key)
+    }
+
+    // true when the filter should be rebuilt
+    pub(crate) fn removed(&mut self) -> bool {
+        self.removed += 1;
+        self.removed >= self.items
+    }
+
+    pub(crate) fn rebuild<'a, K: Hash + 'a, I: ExactSizeIterator<Item = &'a K>>(&mut self, keys: I) {
+        *self = KeyFilter::new(self.items.max(keys.len()), self.false_positive_rate);
+        for key in keys {
+            self.insert(key);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+use crate::spill::SpillFile;
+use crate::{Cache, LruCache};
+
+#[derive(Clone)]
+enum Data {
+    Inline(Arc<[u8]>),
+    // a region of the spill file
+    #[cfg(feature = "mmap")]
+    Spilled { offset: usize, len: usize }
+}
+
+#[derive(Clone)]
+struct Blob {
+    data: Data,
+    compressed: bool
+}
+
+impl Blob {
+    fn len(&self) -> usize {
+        match &self.data {
+            Data::Inline(bytes) => bytes.len(),
+            #[cfg(feature = "mmap")]
+            Data::Spilled { len, .. } => *len
+        }
+    }
+}
+
+// a cache of byte blobs bounded by their total length
+pub struct BytesLruCache<K: Clone + Eq + Hash> {
+    cache: LruCache<K, Blob>,
+    compress_above: Option<usize>,
+    #[cfg(feature = "mmap")]
+    spill: Option<(usize, SpillFile)>
+}
+
+impl<K: Clone + Eq + Hash> BytesLruCache<K> {
+    pub fn new(capacity: usize, max_bytes: usize) -> Self {
+        BytesLruCache {
+            cache: LruCache::new(capacity).weigh_by(max_bytes, |_, blob: &Blob| blob.len()),
+            compress_above: None,
+            #[cfg(feature = "mmap")]
+            spill: None
+        }
+    }
+
+    // blobs longer than `threshold` are stored lz4 compressed and count against
+    // the budget with their compressed length
+    #[cfg(feature = "compression")]
+    pub fn compress_above(mut self, threshold: usize) -> Self {
+        self.compress_above = Some(threshold);
+        self
+    }
+
+    // blobs longer than `threshold` are written to a memory mapped file at `path` (truncated on open)
+    // and only a handle stays in memory. they still count against the byte budget
+    #[cfg(feature = "mmap")]
+    pub fn spill_above<P: AsRef<std::path::Path>>(mut self, threshold: usize, path: P) -> std::io::Result<Self> {
+        self.spill = Some((threshold, SpillFile::create(path)?));
+        Ok(self)
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<Arc<[u8]>>
+    where
+        K: Borrow<Q>
+    {
+        let blob = self.cache.get(key)?;
+        let bytes = self.unpack(&blob);
+        if bytes.is_none() {
+            // a blob that can't be read back is dropped like a miss
+            self.delete(key);
+        }
+        bytes
+    }
+
+    // evicted blobs are released without being read back, spilled ones stay out of memory
+    pub fn put<B: Into<Arc<[u8]>>>(&mut self, key: K, bytes: B) {
+        for (_, blob) in self.insert(key, bytes.into()) {
+            self.release(&blob);
+        }
+    }
+
+    // evicted blobs that can't be read back are dropped from the result
+    pub fn push<B: Into<Arc<[u8]>>>(&mut self, key: K, bytes: B) -> Vec<(K, Arc<[u8]>)> {
+        let evicted = self.insert(key, bytes.into());
+        evicted.into_iter()
+            .filter_map(|(key, blob)| {
+                let bytes = self.unpack(&blob);
+                self.release(&blob);
+                Some((key, bytes?))
+            })
+            .collect()
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        if let Some(blob) = self.cache.remove(key) {
+            self.release(&blob);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn current_bytes(&self) -> usize {
+        self.cache.current_weight()
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.cache.max_weight()
+    }
+
+    pub fn headroom(&self) -> usize {
+        self.cache.headroom()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+        #[cfg(feature = "mmap")]
+        if let Some((_, spill)) = &mut self.spill {
+            spill.clear();
+        }
+    }
+
+    fn insert(&mut self, key: K, bytes: Arc<[u8]>) -> Vec<(K, Blob)> {
+        if let Some(previous) = self.cache.remove(&key) {
+            self.release(&previous);
+        }
+        let blob = self.pack(bytes);
+        self.cache.push(key, blob)
+    }
+
+    fn pack(&mut self, bytes: Arc<[u8]>) -> Blob {
+        let (bytes, compressed) = match self.compress_above {
+            Some(threshold) if bytes.len() > threshold => {
+                let packed = compress(&bytes);
+                if packed.len() < bytes.len() {
+                    (packed, true)
+                } else {
+                    (bytes, false)
+                }
+            },
+            _ => (bytes, false)
+        };
+
+        #[cfg(feature = "mmap")]
+        if let Some((threshold, spill)) = &mut self.spill {
+            if bytes.len() > *threshold {
+                // fall back to keeping the blob in memory if the file can't take it
+                if let Ok(offset) = spill.write(&bytes) {
+                    return Blob { data: Data::Spilled { offset, len: bytes.len() }, compressed };
+                }
+            }
+        }
+        Blob { data: Data::Inline(bytes), compressed }
+    }
+
+    // None if the blob can't be read back, e.g. a spill region that no longer decompresses
+    fn unpack(&self, blob: &Blob) -> Option<Arc<[u8]>> {
+        let bytes = match &blob.data {
+            Data::Inline(bytes) => Arc::clone(bytes),
+            #[cfg(feature = "mmap")]
+            Data::Spilled { offset, len } => {
+                let (_, spill) = self.spill.as_ref()?;
+                spill.read(*offset, *len).into()
+            }
+        };
+
+        if !blob.compressed {
+            return Some(bytes);
+        }
+        decompress(&bytes)
+    }
+
+    fn release(&mut self, blob: &Blob) {
+        #[cfg(feature = "mmap")]
+        if let (Data::Spilled { offset, len }, Some((_, spill))) = (&blob.data, &mut self.spill) {
+            spill.free(*offset, *len);
+        }
+        #[cfg(not(feature = "mmap"))]
+        let _ = blob;
+    }
+}
+
+#[cfg(feature = "compression")]
+fn compress(bytes: &[u8]) -> Arc<[u8]> {
+    lz4_flex::compress_prepend_size(bytes).into()
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(bytes: &[u8]) -> Arc<[u8]> {
+    bytes.into()
+}
+
+#[cfg(feature = "compression")]
+fn decompress(bytes: &[u8]) -> Option<Arc<[u8]>> {
+    lz4_flex::decompress_size_prepended(bytes).ok().map(Into::into)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(bytes: &[u8]) -> Option<Arc<[u8]>> {
+    Some(bytes.into())
+}
+
+impl<K: Clone + Eq + Hash> Cache<K, Arc<[u8]>> for BytesLruCache<K> {
+    fn get(&mut self, key: &K) -> Option<Arc<[u8]>> {
+        BytesLruCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: Arc<[u8]>) -> Vec<(K, Arc<[u8]>)> {
+        BytesLruCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        BytesLruCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        BytesLruCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_cache_budget() {
+        let mut cache = BytesLruCache::new(10, 8);
+        cache.put("a", vec![0u8; 4]);
+        cache.put("b", &b"1234"[..]);
+        assert_eq!(cache.current_bytes(), 8);
+        assert_eq!(cache.headroom(), 0);
+        cache.put("c", vec![1u8; 2]);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b").as_deref(), Some(&b"1234"[..]));
+        assert_eq!(cache.current_bytes(), 6);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_bytes_cache_compression() {
+        let mut cache = BytesLruCache::new(10, 1024).compress_above(64);
+        cache.put("a", vec![7u8; 4096]);
+        cache.put("b", vec![1u8; 16]);
+        assert!(cache.current_bytes() < 1024);
+        assert_eq!(cache.get(&"a").as_deref(), Some(&vec![7u8; 4096][..]));
+        assert_eq!(cache.get(&"b").as_deref(), Some(&vec![1u8; 16][..]));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_bytes_cache_spills_large_blobs() {
+        let path = std::env::temp_dir().join(format!("lru-bytes-spill-{}", std::process::id()));
+        let mut cache = BytesLruCache::new(10, 1 << 20).spill_above(1024, &path).unwrap();
+        cache.put("small", vec![1u8; 16]);
+        cache.put("large", vec![2u8; 100_000]);
+        cache.put("large", vec![3u8; 200_000]);
+        assert_eq!(cache.get(&"small").as_deref(), Some(&vec![1u8; 16][..]));
+        assert_eq!(cache.get(&"large").as_deref(), Some(&vec![3u8; 200_000][..]));
+        assert_eq!(cache.current_bytes(), 200_016);
+        cache.delete(&"large");
+        cache.put("other", vec![4u8; 50_000]);
+        assert_eq!(cache.get(&"other").as_deref(), Some(&vec![4u8; 50_000][..]));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(all(feature = "mmap", feature = "compression"))]
+    #[test]
+    fn test_bytes_cache_drops_corrupt_spills() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let path = std::env::temp_dir().join(format!("lru-bytes-corrupt-{}", std::process::id()));
+        let mut cache = BytesLruCache::new(2, 1 << 20).compress_above(64).spill_above(64, &path).unwrap();
+        let blob: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        cache.put("a", blob.clone());
+        cache.put("b", blob.clone());
+        // the mapping is shared, so garbling the file garbles both spilled blobs
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(&[0xff; 4096]).unwrap();
+        drop(file);
+
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.len(), 1);
+        // evicts b, which is released without being read back
+        cache.put("c", vec![1u8; 16]);
+        cache.put("d", vec![1u8; 16]);
+        assert_eq!(cache.push("e", vec![1u8; 16]), vec![("c", Arc::from(vec![1u8; 16]))]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use std::hash::Hash;
+
+use crate::{Chain, LruCache};
+
+pub trait Cache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V>;
+
+    // returns the entries pushed out to make room
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)>;
+
+    fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    fn delete(&mut self, key: &K);
+
+    fn reset(&mut self);
+
+    fn chain<C: Cache<K, V>>(self, next: C) -> Chain<Self, C>
+    where
+        Self: Sized
+    {
+        Chain::new(self, next)
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for LruCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        LruCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        LruCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        LruCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        LruCache::reset(self)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::Cache;
+
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+    demote: bool
+}
+
+impl<A, B> Chain<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Chain {
+            first,
+            second,
+            demote: true
+        }
+    }
+
+    // when disabled, entries evicted from the first level are dropped
+    pub fn demote_evictions(mut self, demote: bool) -> Self {
+        self.demote = demote;
+        self
+    }
+
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    fn demote<K, V>(&mut self, evicted: Vec<(K, V)>) -> Vec<(K, V)>
+    where
+        B: Cache<K, V>
+    {
+        if !self.demote {
+            return evicted;
+        }
+        evicted.into_iter()
+            .flat_map(|(key, value)| self.second.push(key, value))
+            .collect()
+    }
+}
+
+impl<K, V, A, B> Cache<K, V> for Chain<A, B>
+where
+    K: Clone + PartialEq,
+    V: Clone,
+    A: Cache<K, V>,
+    B: Cache<K, V>
+{
+    fn get(&mut self, key: &K) -> Option<V> {
+        if let Some(value) = self.first.get(key) {
+            return Some(value);
+        }
+
+        let value = self.second.get(key)?;
+        let mut evicted = self.first.push(key.clone(), value.clone());
+        if evicted.iter().any(|(evicted_key, _)| evicted_key == key) {
+            // the first level turned it away, so it stays in the second
+            evicted.retain(|(evicted_key, _)| evicted_key != key);
+        } else {
+            self.second.delete(key);
+        }
+        self.demote(evicted);
+        Some(value)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.second.delete(&key);
+        let evicted = self.first.push(key, value);
+        self.demote(evicted)
+    }
+
+    fn delete(&mut self, key: &K) {
+        self.first.delete(key);
+        self.second.delete(key);
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, LruCache};
+
+    #[test]
+    fn test_chain_demotes_and_promotes() {
+        let mut cache = LruCache::new(1).chain(LruCache::new(2));
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.first().len(), 1);
+        assert_eq!(cache.second().len(), 1);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.first().len(), 1);
+        assert_eq!(cache.get(&2), Some(2));
+        cache.delete(&1);
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_chain_without_demotion() {
+        let mut cache = LruCache::new(1).chain(LruCache::new(2)).demote_evictions(false);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(2));
+    }
+
+    #[test]
+    fn test_chain_keeps_entries_the_first_level_rejects() {
+        for demote in [false, true] {
+            let mut second = LruCache::new(2);
+            second.put(1, 20);
+            second.put(2, 2);
+            let first = LruCache::new(2).weigh_by(10, |_, value: &usize| *value);
+            let mut cache = first.chain(second).demote_evictions(demote);
+            assert_eq!(cache.get(&1), Some(20));
+            assert_eq!(cache.get(&2), Some(2));
+            assert_eq!(cache.second().entries(), vec![(1, 20)]);
+            assert_eq!(cache.get(&1), Some(20));
+        }
+    }
+}
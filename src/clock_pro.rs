@@ -0,0 +1,363 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::Cache;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Hot,
+    Cold,
+    // evicted cold page still remembered, a hit on it means the cold set is too small
+    Test
+}
+
+struct Slot<K, V> {
+    key: K,
+    value: Option<V>,
+    status: Status,
+    referenced: bool,
+    prev: usize,
+    next: usize
+}
+
+// CLOCK-Pro: approximates LIRS with clock hands instead of stacks. resident pages are hot or cold,
+// evicted cold pages stay on the clock as non-resident test pages for up to `capacity` more entries,
+// and a hit on one grows the cold target (up to half the capacity, so a hot set always survives).
+// copes with loops and scans that flush a plain lru
+pub struct ClockProCache<K: Clone + Eq + Hash, V> {
+    capacity: usize,
+    cold_target: usize,
+    slots: Vec<Slot<K, V>>,
+    free: Vec<usize>,
+    map: HashMap<K, usize>,
+    hand_hot: Option<usize>,
+    hand_cold: Option<usize>,
+    hand_test: Option<usize>,
+    hot: usize,
+    cold: usize,
+    test: usize,
+    evicted: Vec<(K, V)>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> ClockProCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        ClockProCache {
+            capacity,
+            cold_target: 1,
+            slots: Vec::new(),
+            free: Vec::new(),
+            map: HashMap::new(),
+            hand_hot: None,
+            hand_cold: None,
+            hand_test: None,
+            hot: 0,
+            cold: 0,
+            test: 0,
+            evicted: Vec::new()
+        }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let slot = &mut self.slots[*self.map.get(key)?];
+        let value = slot.value.clone()?;
+        slot.referenced = true;
+        Some(value)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.slots[*self.map.get(key)?].value.clone()
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entries evicted to make room
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        match self.map.get(&key).copied() {
+            None => {
+                let index = self.allocate(key, value, Status::Cold);
+                self.link(index);
+                self.cold += 1;
+            },
+            Some(index) if self.slots[index].value.is_some() => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                slot.referenced = true;
+            },
+            Some(index) => {
+                // reuse distance was short enough to have stayed resident with a larger cold set
+                if self.cold_target < self.max_cold_target() {
+                    self.cold_target += 1;
+                }
+                self.unlink(index);
+                self.test -= 1;
+                let index = self.allocate(key, value, Status::Hot);
+                self.link(index);
+                self.hot += 1;
+            }
+        }
+        std::mem::take(&mut self.evicted)
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let index = *self.map.get(key)?;
+        match self.slots[index].status {
+            Status::Hot => self.hot -= 1,
+            Status::Cold => self.cold -= 1,
+            Status::Test => self.test -= 1
+        }
+        self.unlink(index);
+        self.slots[index].value.take()
+    }
+
+    // resident entries
+    pub fn len(&self) -> usize {
+        self.hot + self.cold
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn reset(&mut self) {
+        *self = ClockProCache::new(self.capacity);
+    }
+
+    fn max_cold_target(&self) -> usize {
+        (self.capacity / 2).max(1)
+    }
+
+    fn allocate(&mut self, key: K, value: V, status: Status) -> usize {
+        let slot = Slot { key, value: Some(value), status, referenced: false, prev: 0, next: 0 };
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = slot;
+                index
+            },
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    // makes room, then places the slot just behind the hot hand
+    fn link(&mut self, index: usize) {
+        while self.hot + self.cold >= self.capacity {
+            self.run_hand_cold();
+        }
+
+        match self.hand_hot {
+            None => {
+                self.slots[index].prev = index;
+                self.slots[index].next = index;
+                self.hand_hot = Some(index);
+                self.hand_cold = Some(index);
+                self.hand_test = Some(index);
+            },
+            Some(hot) => {
+                let next = self.slots[hot].next;
+                self.slots[index].prev = hot;
+                self.slots[index].next = next;
+                self.slots[next].prev = index;
+                self.slots[hot].next = index;
+                if self.hand_cold == Some(hot) {
+                    self.hand_cold = Some(index);
+                }
+                if self.hand_test == Some(hot) {
+                    self.hand_test = Some(index);
+                }
+            }
+        }
+        let key = self.slots[index].key.clone();
+        self.map.insert(key, index);
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Slot { prev, next, .. } = self.slots[index];
+        self.map.remove(&self.slots[index].key);
+        self.free.push(index);
+
+        let replacement = (next != index).then_some(prev);
+        for hand in [&mut self.hand_hot, &mut self.hand_cold, &mut self.hand_test] {
+            if *hand == Some(index) {
+                *hand = replacement;
+            }
+        }
+        self.slots[prev].next = next;
+        self.slots[next].prev = prev;
+    }
+
+    // slots on the clock, resident or not
+    fn ring_len(&self) -> usize {
+        self.hot + self.cold + self.test
+    }
+
+    // evicts one cold page, promoting referenced ones to hot on the way
+    fn run_hand_cold(&mut self) {
+        if self.cold == 0 {
+            self.run_hand_hot();
+        }
+        for _ in 0..self.ring_len() * 2 {
+            let Some(index) = self.hand_cold else { return };
+            self.hand_cold = Some(self.slots[index].next);
+            let slot = &mut self.slots[index];
+            if slot.status != Status::Cold {
+                continue;
+            }
+
+            if slot.referenced {
+                slot.status = Status::Hot;
+                slot.referenced = false;
+                self.cold -= 1;
+                self.hot += 1;
+                while self.hot > self.capacity - self.cold_target && self.run_hand_hot() {}
+                continue;
+            }
+
+            slot.status = Status::Test;
+            if let Some(value) = slot.value.take() {
+                self.evicted.push((slot.key.clone(), value));
+            }
+            self.cold -= 1;
+            self.test += 1;
+            while self.test > self.capacity && self.run_hand_test() {}
+            return;
+        }
+    }
+
+    // demotes one unreferenced hot page to cold, clearing reference bits on the way
+    fn run_hand_hot(&mut self) -> bool {
+        for _ in 0..self.ring_len() * 2 {
+            let Some(index) = self.hand_hot else { return false };
+            self.hand_hot = Some(self.slots[index].next);
+            let slot = &mut self.slots[index];
+            match slot.status {
+                Status::Hot if slot.referenced => slot.referenced = false,
+                Status::Hot => {
+                    slot.status = Status::Cold;
+                    self.hot -= 1;
+                    self.cold += 1;
+                    return true;
+                },
+                _ => ()
+            }
+        }
+        false
+    }
+
+    // forgets the next non-resident page, its test period ended without a hit
+    fn run_hand_test(&mut self) -> bool {
+        for _ in 0..self.ring_len() {
+            let Some(index) = self.hand_test else { return false };
+            self.hand_test = Some(self.slots[index].next);
+            if self.slots[index].status == Status::Test {
+                self.unlink(index);
+                self.test -= 1;
+                if self.cold_target > 1 {
+                    self.cold_target -= 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for ClockProCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        ClockProCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        ClockProCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        ClockProCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        ClockProCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::LruCache;
+
+    fn hits<C: Cache<u32, u32>>(cache: &mut C, trace: &[u32]) -> usize {
+        trace.iter().filter(|&&key| {
+            let hit = cache.get(&key).is_some();
+            if !hit {
+                cache.put(key, key);
+            }
+            hit
+        }).count()
+    }
+
+    #[test]
+    fn test_clock_pro_cache() {
+        let mut cache = ClockProCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.push(3, 3).len(), 1);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.remove(&3), Some(3));
+        assert_eq!(cache.get(&3), None);
+        cache.reset();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_clock_pro_survives_loops() {
+        let trace: Vec<u32> = (0..50).flat_map(|_| 0..12).collect();
+        let lru = hits(&mut LruCache::new(10), &trace);
+        let clock_pro = hits(&mut ClockProCache::new(10), &trace);
+        assert_eq!(lru, 0);
+        assert!(clock_pro > trace.len() / 4, "{} hits", clock_pro);
+    }
+
+    proptest! {
+        #[test]
+        fn test_clock_pro_stays_bounded(capacity in 1..8usize, ops in proptest::collection::vec((0..3u8, 0..16u32), 0..300)) {
+            let mut cache = ClockProCache::new(capacity);
+            for (op, key) in ops {
+                match op {
+                    0 => { cache.get(&key); },
+                    1 => cache.put(key, key),
+                    _ => cache.delete(&key)
+                }
+                prop_assert!(cache.len() <= capacity);
+                prop_assert!(cache.test <= capacity);
+            }
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::ops::{Add, Deref, DerefMut, Sub};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::router::{HashRouter, ShardRouter};
+use crate::{CacheError, CasError, DebugReport, InsertOutcome, LruCache, MemoryPressure};
+
+// stripes per shard buffer, so concurrent readers rarely meet on one
+const STRIPES: usize = 4;
+
+// hits read under the shard's read lock whose bookkeeping (stats, idle deadline, promotion) hasn't been
+// applied yet. readers append to one of a few stripes with try_lock and never wait: a busy stripe is skipped
+// for the next one. whoever next holds the shard's write lock applies them in one go, stripe by stripe, so hits
+// queued in different stripes may be applied out of order
+struct AccessBuffer<K> {
+    stripes: Vec<CachePadded<Mutex<Vec<K>>>>,
+    per_stripe: usize
+}
+
+impl<K: Clone + Eq + Hash> AccessBuffer<K> {
+    fn new(capacity: usize) -> Self {
+        AccessBuffer {
+            stripes: (0..STRIPES).map(|_| CachePadded(Mutex::new(Vec::new()))).collect(),
+            per_stripe: capacity.div_ceil(STRIPES)
+        }
+    }
+
+    // hands the key back when every stripe was busy or full, the caller applies the hit itself then
+    fn record(&self, key: K) -> Result<(), K> {
+        let start = stripe_hint();
+        for offset in 0..STRIPES {
+            if let Ok(mut keys) = self.stripes[(start + offset) % STRIPES].try_lock() {
+                if keys.len() < self.per_stripe {
+                    keys.push(key);
+                    return Ok(());
+                }
+            }
+        }
+        Err(key)
+    }
+
+    // called with the shard write locked. a stripe poisoned by a panicking reader still only holds keys
+    fn drain<V: Clone>(&self, shard: &mut LruCache<K, V>) {
+        for stripe in &self.stripes {
+            let keys = mem::take(&mut *stripe.lock().unwrap_or_else(PoisonError::into_inner));
+            for key in &keys {
+                shard.record_hit(key);
+            }
+        }
+    }
+}
+
+// the stripe a thread tries first, fixed per thread so threads spread over the stripes
+fn stripe_hint() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static STRIPE: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    STRIPE.with(|stripe| *stripe)
+}
+
+// keeps a shard's lock or counters on cache lines of their own, so threads busy with neighbouring shards don't
+// keep invalidating each other's lines. 128 bytes covers x86's adjacent line prefetcher and apple silicon's lines
+#[derive(Default)]
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+type Shards<K, V> = Arc<Vec<CachePadded<RwLock<LruCache<K, V>>>>>;
+
+// a shard count for `with_default_shards`: four shards per core, so two threads rarely want the same lock,
+// rounded up to a power of two. 4 when the parallelism can't be queried
+pub fn default_shard_count() -> usize {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    (cores * 4).next_power_of_two()
+}
+
+// a shard's len and weight as of the last time it was unlocked, readable without the lock
+#[derive(Default)]
+struct ShardCounters {
+    len: AtomicUsize,
+    weight: AtomicUsize
+}
+
+// a locked shard that publishes its len and weight to its counters when unlocked
+struct ShardGuard<'a, K: Clone + Eq + Hash, V> {
+    shard: RwLockWriteGuard<'a, LruCache<K, V>>,
+    counters: &'a ShardCounters
+}
+
+impl<K: Clone + Eq + Hash, V> Deref for ShardGuard<'_, K, V> {
+    type Target = LruCache<K, V>;
+
+    fn deref(&self) -> &LruCache<K, V> {
+        &self.shard
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> DerefMut for ShardGuard<'_, K, V> {
+    fn deref_mut(&mut self) -> &mut LruCache<K, V> {
+        &mut self.shard
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> Drop for ShardGuard<'_, K, V> {
+    fn drop(&mut self) {
+        self.counters.len.store(self.shard.map.len(), Ordering::Relaxed);
+        self.counters.weight.store(self.shard.weight, Ordering::Relaxed);
+    }
+}
+
+// a thread safe cache split into independently locked lru shards
+pub struct ConcurrentLruCache<K: Clone + Eq + Hash, V, R = HashRouter> {
+    // shared with read handles
+    shards: Shards<K, V>,
+    // one per shard with buffered_recency, empty otherwise
+    buffers: Vec<AccessBuffer<K>>,
+    counters: Vec<CachePadded<ShardCounters>>,
+    router: Arc<R>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> ConcurrentLruCache<K, V> {
+    // capacity is split evenly across the shards, rounding up
+    pub fn new(capacity: usize, shards: usize) -> Self {
+        ConcurrentLruCache::with_router(capacity, shards, HashRouter::default())
+    }
+
+    // sharded by `default_shard_count`, so throughput scales with the machine's cores
+    pub fn with_default_shards(capacity: usize) -> Self {
+        ConcurrentLruCache::new(capacity, default_shard_count())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone, R: ShardRouter<K>> ConcurrentLruCache<K, V, R> {
+    pub fn with_router(capacity: usize, shards: usize, router: R) -> Self {
+        let shards = shards.max(1);
+        let per_shard = capacity.div_ceil(shards);
+        ConcurrentLruCache {
+            shards: Arc::new((0..shards).map(|_| CachePadded(RwLock::new(LruCache::new(per_shard)))).collect()),
+            buffers: Vec::new(),
+            counters: (0..shards).map(|_| CachePadded::default()).collect(),
+            router: Arc::new(router)
+        }
+    }
+
+    // BP-Wrapper style batching: a hit is looked up under the shard's read lock, so reads of one shard run in
+    // parallel, and its bookkeeping is queued in a striped buffer of `per_shard` slots. queued hits are applied in
+    // a batch by the next write to the shard, or by a read that finds the buffer full. misses take the write lock
+    // like before. eviction order and stats lag by at most the buffered reads
+    pub fn buffered_recency(mut self, per_shard: usize) -> Self {
+        self.buffers = (0..self.shards.len()).map(|_| AccessBuffer::new(per_shard.max(1))).collect();
+        self
+    }
+
+    // a cloneable handle that can look but not touch, for components that only need visibility into the cache
+    pub fn read_handle(&self) -> ReadHandle<K, V, R> {
+        ReadHandle { shards: Arc::clone(&self.shards), router: Arc::clone(&self.router) }
+    }
+
+    // the shard that owns `key`
+    pub fn shard_for<Q: ?Sized>(&self, key: &Q) -> usize
+    where
+        R: ShardRouter<Q>
+    {
+        self.router.route(key, self.shards.len())
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        let index = self.shard_for(key);
+        let Some(buffer) = self.buffers.get(index) else { return self.lock(index).get(key) };
+        // a miss or an expired entry needs the write lock to be counted and dropped. the buffer holds keys as
+        // stored, whatever form the lookup came in by
+        let Some((key, value)) = self.shards[index].read().unwrap().peek_entry(key) else {
+            return self.lock(index).get(key);
+        };
+        if let Err(key) = buffer.record(key) {
+            self.lock(index).record_hit::<K>(&key);
+        }
+        Some(value)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shards[self.shard_for(key)].read().unwrap().peek(key)
+    }
+
+    pub fn put(&self, key: K, value: V) -> InsertOutcome {
+        self.shard(&key).put(key, value)
+    }
+
+    pub fn push(&self, key: K, value: V) -> Vec<(K, V)> {
+        self.shard(&key).push(key, value)
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).delete(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).remove(key)
+    }
+
+    // the check and the insert happen under the shard's lock, so only one of several racing callers wins
+    pub fn put_if_absent(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).put_if_absent(key, value)
+    }
+
+    pub fn update<Q: ?Sized + Hash + Eq, F: FnOnce(&mut V)>(&self, key: &Q, update: F) -> bool
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).update(key, update)
+    }
+
+    pub fn touch_with_ttl<Q: ?Sized + Hash + Eq>(&self, key: &Q, ttl: Duration) -> bool
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).touch_with_ttl(key, ttl)
+    }
+
+    // the merge runs under the shard's lock, concurrent upserts of one key never lose a value
+    pub fn upsert<F: FnOnce(&V, V) -> V>(&self, key: K, value: V, merge: F) {
+        self.shard(&key).upsert(key, value, merge);
+    }
+
+    pub fn append<T, I: IntoIterator<Item = T>>(&self, key: K, items: I)
+    where
+        V: Extend<T> + Default
+    {
+        self.shard(&key).append(key, items);
+    }
+
+    pub fn increment(&self, key: K, delta: V) -> V
+    where
+        V: Add<Output = V> + Default
+    {
+        self.shard(&key).increment(key, delta)
+    }
+
+    pub fn decrement(&self, key: K, delta: V) -> V
+    where
+        V: Sub<Output = V> + Default
+    {
+        self.shard(&key).decrement(key, delta)
+    }
+
+    pub fn put_if_newer(&self, key: K, value: V, stamp: u64) -> bool {
+        self.shard(&key).put_if_newer(key, value, stamp)
+    }
+
+    // the check and the swap happen under the shard's lock
+    pub fn compare_and_swap<Q: ?Sized + Hash + Eq>(&self, key: &Q, expected: &V, new: V) -> Result<(), CasError<V>>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>,
+        V: PartialEq
+    {
+        self.shard(key).compare_and_swap(key, expected, new)
+    }
+
+    pub fn update_if<Q: ?Sized + Hash + Eq, F: FnOnce(&V) -> Option<V>>(&self, key: &Q, update: F) -> bool
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).update_if(key, update)
+    }
+
+    // the check and the removal happen under the shard's lock
+    pub fn remove_if<Q: ?Sized + Hash + Eq, F: FnOnce(&V) -> bool>(&self, key: &Q, predicate: F) -> Option<V>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).remove_if(key, predicate)
+    }
+
+    // the try_ variants report a shard poisoned by a panicking thread instead of panicking too
+    pub fn try_get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&self, key: &Q) -> Result<Option<V>, CacheError>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        Ok(self.try_shard(key)?.get(key))
+    }
+
+    pub fn try_put(&self, key: K, value: V) -> Result<(), CacheError> {
+        self.try_shard(&key)?.try_put(key, value)
+    }
+
+    pub fn try_delete<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Result<(), CacheError>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.try_shard(key)?.delete(key);
+        Ok(())
+    }
+
+    // puts every entry or none. all involved shards are locked, in index order so concurrent batches can't
+    // deadlock, and nobody sees the batch half applied. a value too heavy to cache or a batch too big for its
+    // shard fails the whole batch up front. an admission policy can still turn single entries away
+    pub fn put_all_or_nothing<I: IntoIterator<Item = (K, V)>>(&self, entries: I) -> Result<Vec<(K, V)>, CacheError> {
+        let mut batches: BTreeMap<usize, Vec<(K, V)>> = BTreeMap::new();
+        for (key, value) in entries {
+            batches.entry(self.shard_for(&key)).or_default().push((key, value));
+        }
+        let mut shards = self.lock_shards(batches.keys().copied());
+        for (index, batch) in &batches {
+            shards[index].check_batch(batch.iter().map(|(key, value)| (key, value)))?;
+        }
+
+        let mut evicted = Vec::new();
+        for (index, batch) in batches {
+            let shard = shards.get_mut(&index).expect("locked above");
+            for (key, value) in batch {
+                evicted.extend(shard.push(key, value));
+            }
+        }
+        Ok(evicted)
+    }
+
+    // removes all of `keys` as one step, returning the entries that were present
+    pub fn remove_all<I: IntoIterator<Item = K>>(&self, keys: I) -> Vec<(K, V)> {
+        let batches = self.batch_keys(keys);
+        let mut shards = self.lock_shards(batches.keys().copied());
+        let mut removed = Vec::new();
+        for (index, batch) in batches {
+            let shard = shards.get_mut(&index).expect("locked above");
+            for key in batch {
+                if let Some(value) = shard.remove(&key) {
+                    removed.push((key, value));
+                }
+            }
+        }
+        removed
+    }
+
+    // removes the live entries for `keys`, locking each shard involved once, and returns how many were present
+    pub fn invalidate_many<I: IntoIterator<Item = K>>(&self, keys: I) -> usize {
+        let batches = self.batch_keys(keys);
+        let mut shards = self.lock_shards(batches.keys().copied());
+        batches.into_iter()
+            .map(|(index, batch)| shards.get_mut(&index).expect("locked above").invalidate_many(batch))
+            .sum()
+    }
+
+    // exact, locks every shard in turn
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().unwrap().is_empty())
+    }
+
+    pub fn current_weight(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().current_weight()).sum()
+    }
+
+    // len and weight as each shard last left them, read without taking any lock so metrics collection
+    // never contends with the hot path. can be off by whatever is happening meanwhile
+    pub fn len_approx(&self) -> usize {
+        self.counters.iter().map(|counters| counters.len.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn weight_approx(&self) -> usize {
+        self.counters.iter().map(|counters| counters.weight.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    // copies the live entries one shard at a time, so only one shard is ever locked.
+    // each shard's part is a point in time copy of that shard, in lru order within the shard
+    pub fn snapshot(&self) -> Vec<(K, V)> {
+        let mut entries = Vec::new();
+        for index in 0..self.shards.len() {
+            entries.extend(self.lock(index).entries());
+        }
+        entries
+    }
+
+    // one report per shard, locking one shard at a time
+    pub fn debug_report(&self) -> Vec<DebugReport> {
+        (0..self.shards.len()).map(|index| self.lock(index).debug_report()).collect()
+    }
+
+    pub fn evict_older_than(&self, age: Duration) -> usize {
+        (0..self.shards.len()).map(|index| self.lock(index).evict_older_than(age)).sum()
+    }
+
+    // trims every shard, one at a time
+    pub fn on_pressure(&self, level: MemoryPressure) -> usize {
+        (0..self.shards.len()).map(|index| self.lock(index).on_pressure(level).len()).sum()
+    }
+
+    pub fn reset(&self) {
+        for index in 0..self.shards.len() {
+            self.lock(index).reset();
+        }
+    }
+
+    fn shard<Q: ?Sized>(&self, key: &Q) -> ShardGuard<'_, K, V>
+    where
+        R: ShardRouter<Q>
+    {
+        self.lock(self.shard_for(key))
+    }
+
+    // locks a shard and applies its buffered hits, so it is up to date for whatever comes next
+    fn lock(&self, index: usize) -> ShardGuard<'_, K, V> {
+        let mut shard = self.guard(index, self.shards[index].write().unwrap());
+        if let Some(buffer) = self.buffers.get(index) {
+            buffer.drain(&mut shard);
+        }
+        shard
+    }
+
+    // `indices` must be ascending, every multi shard operation locks in that order
+    fn batch_keys<I: IntoIterator<Item = K>>(&self, keys: I) -> BTreeMap<usize, Vec<K>> {
+        let mut batches: BTreeMap<usize, Vec<K>> = BTreeMap::new();
+        for key in keys {
+            batches.entry(self.shard_for(&key)).or_default().push(key);
+        }
+        batches
+    }
+
+    fn lock_shards(&self, indices: impl Iterator<Item = usize>) -> BTreeMap<usize, ShardGuard<'_, K, V>> {
+        indices.map(|index| (index, self.lock(index))).collect()
+    }
+
+    fn try_shard<Q: ?Sized>(&self, key: &Q) -> Result<ShardGuard<'_, K, V>, CacheError>
+    where
+        R: ShardRouter<Q>
+    {
+        let index = self.shard_for(key);
+        let mut shard = self.guard(index, self.shards[index].write().map_err(|_| CacheError::Poisoned)?);
+        if let Some(buffer) = self.buffers.get(index) {
+            buffer.drain(&mut shard);
+        }
+        Ok(shard)
+    }
+
+    fn guard<'a>(&'a self, index: usize, shard: RwLockWriteGuard<'a, LruCache<K, V>>) -> ShardGuard<'a, K, V> {
+        ShardGuard { shard, counters: &self.counters[index] }
+    }
+}
+
+// read only access to a ConcurrentLruCache. lookups don't change recency, and the handle stays valid
+// (and keeps the entries alive) after the cache itself is dropped
+pub struct ReadHandle<K: Clone + Eq + Hash, V, R = HashRouter> {
+    shards: Shards<K, V>,
+    router: Arc<R>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone, R: ShardRouter<K>> ReadHandle<K, V, R> {
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).peek(key)
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        R: ShardRouter<Q>
+    {
+        self.shard(key).contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().unwrap().is_empty())
+    }
+
+    fn shard<Q: ?Sized>(&self, key: &Q) -> RwLockReadGuard<'_, LruCache<K, V>>
+    where
+        R: ShardRouter<Q>
+    {
+        self.shards[self.router.route(key, self.shards.len())].read().unwrap()
+    }
+}
+
+impl<K: Clone + Eq + Hash, V, R> Clone for ReadHandle<K, V, R> {
+    fn clone(&self) -> Self {
+        ReadHandle { shards: Arc::clone(&self.shards), router: Arc::clone(&self.router) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::ConsistentHashRouter;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_cache_across_threads() {
+        let cache = Arc::new(ConcurrentLruCache::new(1000, 8));
+        let workers: Vec<_> = (0..4).map(|worker| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                for i in 0..100 {
+                    cache.put(worker * 100 + i, i);
+                }
+            })
+        }).collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), 400);
+        assert_eq!(cache.get(&305), Some(5));
+        let mut snapshot = cache.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot.len(), 400);
+        assert_eq!(snapshot[0], (0, 0));
+    }
+
+    #[test]
+    fn test_concurrent_cache_poisoned_shard() {
+        let cache = Arc::new(ConcurrentLruCache::new(10, 1));
+        cache.put(1, 1);
+        let poisoner = Arc::clone(&cache);
+        let _ = thread::spawn(move || {
+            let _shard = poisoner.shard(&1);
+            panic!("poison the shard");
+        }).join();
+        assert_eq!(cache.try_get(&1), Err(CacheError::Poisoned));
+        assert_eq!(cache.try_put(2, 2), Err(CacheError::Poisoned));
+    }
+
+    #[test]
+    fn test_concurrent_cache_with_router() {
+        let cache = ConcurrentLruCache::with_router(100, 4, ConsistentHashRouter);
+        let other = ConcurrentLruCache::<u32, u32, _>::with_router(100, 4, ConsistentHashRouter);
+        cache.put(1u32, 1u32);
+        assert_eq!(cache.shard_for(&1), other.shard_for(&1));
+        assert!(cache.shard_for(&1) < 4);
+        assert_eq!(cache.get(&1), Some(1));
+    }
+
+    #[test]
+    fn test_concurrent_cache_borrowed_keys() {
+        let cache = ConcurrentLruCache::new(10, 4).buffered_recency(4);
+        cache.put("a".to_string(), 1);
+        assert_eq!(cache.shard_for("a"), cache.shard_for(&"a".to_string()));
+        assert_eq!(cache.get("a"), Some(1));
+        assert!(cache.read_handle().contains_key("a"));
+        assert!(cache.update("a", |value| *value += 1));
+        assert_eq!(cache.remove("a"), Some(2));
+    }
+
+    #[test]
+    fn test_concurrent_cache_batches() {
+        let cache = ConcurrentLruCache::new(40, 4);
+        assert_eq!(cache.put_all_or_nothing((0..6).map(|key| (key, key))), Ok(vec![]));
+        assert_eq!(cache.len(), 6);
+        assert_eq!(cache.put_all_or_nothing((10..60).map(|key| (key, key))), Err(CacheError::BatchTooLarge));
+        assert_eq!(cache.len(), 6);
+
+        let mut removed = cache.remove_all([1, 3, 5, 7]);
+        removed.sort();
+        assert_eq!(removed, vec![(1, 1), (3, 3), (5, 5)]);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(2));
+        assert_eq!(cache.invalidate_many([0, 1, 2, 4, 9]), 3);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_cache_compare_and_swap() {
+        let cache = Arc::new(ConcurrentLruCache::new(10, 2));
+        cache.put(1, 0);
+        let workers: Vec<_> = (0..4).map(|_| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let mut current = cache.get(&1).unwrap();
+                    while let Err(CasError::Mismatch(value)) = cache.compare_and_swap(&1, &current, current + 1) {
+                        current = value;
+                    }
+                }
+            })
+        }).collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(cache.get(&1), Some(400));
+        assert!(cache.update_if(&1, |value| Some(value + 1)));
+        assert_eq!(cache.compare_and_swap(&2, &0, 1), Err(CasError::Missing));
+        assert_eq!(cache.remove_if(&1, |value| *value == 400), None);
+        assert_eq!(cache.remove_if(&1, |value| *value == 401), Some(401));
+    }
+
+    #[test]
+    fn test_concurrent_cache_put_if_absent() {
+        let cache = Arc::new(ConcurrentLruCache::new(10, 2));
+        let workers: Vec<_> = (0..4).map(|worker| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || cache.put_if_absent(1, worker).is_none())
+        }).collect();
+        let winners = workers.into_iter().map(|worker| worker.join().unwrap()).filter(|&won| won).count();
+        assert_eq!(winners, 1);
+    }
+
+    #[test]
+    fn test_concurrent_cache_buffered_recency() {
+        // room for 4 hits per stripe: one thread's reads then queue in its own stripe, in order. hits spilling
+        // over into other stripes are applied stripe by stripe, so their relative order would depend on which
+        // stripe the test thread happens to start from
+        let cache = ConcurrentLruCache::new(3, 1).buffered_recency(16);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.shards[0].read().unwrap().entries()[0], (1, 1));
+        cache.put(4, 4);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(1));
+
+        for key in [3, 4, 1, 3] {
+            cache.get(&key);
+        }
+        assert_eq!(cache.snapshot(), vec![(4, 4), (1, 1), (3, 3)]);
+        // hits read under the read lock are counted once applied
+        assert_eq!(cache.lock(0).stats().hits, 6);
+
+        let cache = Arc::new(cache);
+        let readers: Vec<_> = (0..4).map(|_| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || (0..100).all(|_| cache.get(&1) == Some(1)))
+        }).collect();
+        assert!(readers.into_iter().all(|reader| reader.join().unwrap()));
+        assert_eq!(cache.lock(0).stats().hits, 406);
+    }
+
+    #[test]
+    fn test_concurrent_cache_read_handle() {
+        let cache = ConcurrentLruCache::new(2, 1);
+        let reader = cache.read_handle();
+        cache.put(1, 1);
+        cache.put(2, 2);
+        let watcher = {
+            let reader = reader.clone();
+            thread::spawn(move || reader.peek(&1))
+        };
+        assert_eq!(watcher.join().unwrap(), Some(1));
+        assert!(reader.contains_key(&2));
+        cache.put(3, 3);
+        assert!(!reader.contains_key(&1));
+        drop(cache);
+        assert_eq!(reader.len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_cache_approximate_len() {
+        let cache = ConcurrentLruCache::new(100, 4);
+        for key in 0..50 {
+            cache.put(key, key);
+        }
+        cache.delete(&7);
+        assert_eq!(cache.len_approx(), 49);
+        assert_eq!(cache.weight_approx(), cache.current_weight());
+        cache.reset();
+        assert_eq!(cache.len_approx(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_cache_padded_shards() {
+        let cache: ConcurrentLruCache<i32, i32> = ConcurrentLruCache::with_default_shards(1000);
+        assert!(cache.shard_count().is_power_of_two());
+        assert!(cache.shard_count() >= 4);
+        let (first, second) = (&*cache.shards[0] as *const _ as usize, &*cache.shards[1] as *const _ as usize);
+        assert!(second - first >= 128);
+        assert_eq!(std::mem::align_of::<CachePadded<ShardCounters>>(), 128);
+    }
+}
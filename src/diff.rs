@@ -0,0 +1,15 @@
+// how two caches' live entries differ, keys in least recently used order of the cache they were found in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheDiff<K> {
+    pub only_in_self: Vec<K>,
+    pub only_in_other: Vec<K>,
+    // in both, with different values
+    pub changed: Vec<K>
+}
+
+impl<K> CacheDiff<K> {
+    // true when both caches hold the same entries, recency aside
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.changed.is_empty()
+    }
+}
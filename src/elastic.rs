@@ -0,0 +1,127 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use crate::CapacityPolicy;
+use crate::admission::HashKey;
+
+// misses judged together before deciding whether to grow
+const WINDOW: u64 = 100;
+
+// for CapacityPolicy::Elastic. remembers the keys of the last `grow_by` evictions: a miss on one of them would
+// have been a hit with `grow_by` more room, so when those make up most misses, growing pays off.
+// ghosts are kept as key hashes, misses come in by whatever borrowed form the lookup used
+pub(crate) struct Growth {
+    max: usize,
+    grow_by: usize,
+    // ghost key hashes with the sequence number of their latest eviction
+    ghosts: HashMap<u64, u64>,
+    // oldest first, may hold older evictions of a key that was evicted again since
+    order: VecDeque<(u64, u64)>,
+    next_seq: u64,
+    misses: u64,
+    ghost_hits: u64
+}
+
+impl Growth {
+    pub(crate) fn new(max: usize, grow_by: usize) -> Self {
+        Growth {
+            max,
+            grow_by: grow_by.max(1),
+            ghosts: HashMap::new(),
+            order: VecDeque::new(),
+            next_seq: 0,
+            misses: 0,
+            ghost_hits: 0
+        }
+    }
+
+    // None for a fixed capacity
+    pub(crate) fn for_policy(policy: CapacityPolicy) -> Option<Box<Growth>> {
+        match policy {
+            CapacityPolicy::Fixed => None,
+            CapacityPolicy::Elastic { max, grow_by } => Some(Box::new(Growth::new(max, grow_by)))
+        }
+    }
+
+    pub(crate) fn policy(&self) -> CapacityPolicy {
+        CapacityPolicy::Elastic { max: self.max, grow_by: self.grow_by }
+    }
+
+    pub(crate) fn evicted(&mut self, key: &dyn HashKey) {
+        let hash = ghost(key);
+        self.next_seq += 1;
+        self.ghosts.insert(hash, self.next_seq);
+        self.order.push_back((hash, self.next_seq));
+        while self.order.len() > self.grow_by {
+            let Some((hash, seq)) = self.order.pop_front() else { break };
+            if self.ghosts.get(&hash) == Some(&seq) {
+                self.ghosts.remove(&hash);
+            }
+        }
+    }
+
+    // counts a miss, and at the end of a window returns the capacity to grow to if capacity misses dominated
+    pub(crate) fn missed(&mut self, key: &dyn HashKey, capacity: usize) -> Option<usize> {
+        self.misses += 1;
+        if self.ghosts.remove(&ghost(key)).is_some() {
+            self.ghost_hits += 1;
+        }
+        if self.misses < WINDOW {
+            return None;
+        }
+        let dominated = self.ghost_hits * 2 > self.misses;
+        self.misses = 0;
+        self.ghost_hits = 0;
+        (dominated && capacity < self.max).then(|| capacity.saturating_add(self.grow_by).min(self.max))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.ghosts.clear();
+        self.order.clear();
+        self.misses = 0;
+        self.ghost_hits = 0;
+    }
+}
+
+fn ghost(key: &dyn HashKey) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CapacityPolicy, LruCache};
+
+    fn run(cache: &mut LruCache<u32, u32>, keys: impl Iterator<Item = u32>) {
+        for key in keys {
+            if cache.get(&key).is_none() {
+                cache.put(key, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_elastic_capacity_grows_on_capacity_misses() {
+        // a loop over 18 keys always misses in a cache of 10, but every miss is on a recently evicted key
+        let mut cache = LruCache::new(10).capacity_policy(CapacityPolicy::Elastic { max: 40, grow_by: 10 });
+        run(&mut cache, (0..18).cycle().take(2000));
+        assert_eq!(cache.capacity(), 20);
+        let before = cache.stats().hits;
+        run(&mut cache, (0..18).cycle().take(100));
+        assert_eq!(cache.stats().hits - before, 100);
+    }
+
+    #[test]
+    fn test_elastic_capacity_ignores_cold_misses() {
+        // a scan much bigger than the cache and the ghost list, more room wouldn't help
+        let mut cache = LruCache::new(10).capacity_policy(CapacityPolicy::Elastic { max: 40, grow_by: 10 });
+        run(&mut cache, (0..1000).cycle().take(5000));
+        assert_eq!(cache.capacity(), 10);
+
+        let mut fixed = LruCache::new(10);
+        run(&mut fixed, (0..18).cycle().take(2000));
+        assert_eq!(fixed.capacity(), 10);
+    }
+}
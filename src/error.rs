@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheError {
+    ZeroCapacity,
+    // the value alone outweighs the whole budget
+    ValueTooHeavy { weight: usize, max_weight: usize },
+    // turned away by the admission policy
+    Rejected,
+    // a batch that can't fit in the cache as a whole, so applying it would evict part of itself
+    BatchTooLarge,
+    // the loader had no value for a missing key
+    LoaderFailed,
+    // a thread panicked while holding the cache's lock
+    Poisoned,
+    // the cache holds what a trace can't carry: a weigher, an admission policy or entries from before recording
+    Unrecordable
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::ZeroCapacity => write!(f, "cache capacity must be at least 1"),
+            CacheError::ValueTooHeavy { weight, max_weight } => write!(f, "value weighs {} but the cache holds at most {}", weight, max_weight),
+            CacheError::Rejected => write!(f, "entry rejected by the admission policy"),
+            CacheError::BatchTooLarge => write!(f, "batch does not fit in the cache"),
+            CacheError::LoaderFailed => write!(f, "loader returned no value"),
+            CacheError::Poisoned => write!(f, "cache lock poisoned by a panicked thread"),
+            CacheError::Unrecordable => write!(f, "only an empty cache without a weigher or admission policy can be recorded")
+        }
+    }
+}
+
+impl Error for CacheError {}
+
+// why compare_and_swap left the entry alone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CasError<V> {
+    Missing,
+    // holds the current value, to retry against
+    Mismatch(V),
+    // the new value was turned away by the admission policy or for its weight
+    Rejected
+}
+
+impl<V> fmt::Display for CasError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CasError::Missing => write!(f, "no entry to swap"),
+            CasError::Mismatch(_) => write!(f, "entry no longer holds the expected value"),
+            CasError::Rejected => write!(f, "the cache turned the new value away")
+        }
+    }
+}
+
+impl<V: fmt::Debug> Error for CasError<V> {}
@@ -0,0 +1,157 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::wheel::TimerWheel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpiryBackend {
+    // O(log n) per scheduled deadline, exact
+    Heap,
+    // O(1) per scheduled deadline, deadlines are rounded up to `tick`
+    TimerWheel { tick: Duration }
+}
+
+// values that know their own deadline, e.g. a dns record's ttl or a token's exp claim.
+// put_expiring uses it in place of the cache's ttl, None falls back to the cache defaults
+pub trait Expiry {
+    fn expires_at(&self) -> Option<Instant>;
+}
+
+impl<T: Expiry + ?Sized> Expiry for Arc<T> {
+    fn expires_at(&self) -> Option<Instant> {
+        T::expires_at(self)
+    }
+}
+
+pub(crate) enum ExpiryQueue<K> {
+    Heap(DeadlineHeap<K>),
+    Wheel(TimerWheel<K>)
+}
+
+impl<K> ExpiryQueue<K> {
+    pub(crate) fn new(backend: ExpiryBackend) -> Self {
+        match backend {
+            ExpiryBackend::Heap => ExpiryQueue::Heap(DeadlineHeap::new()),
+            ExpiryBackend::TimerWheel { tick } => ExpiryQueue::Wheel(TimerWheel::new(tick))
+        }
+    }
+
+    pub(crate) fn schedule(&mut self, key: K, deadline: Instant) -> u64 {
+        match self {
+            ExpiryQueue::Heap(heap) => heap.schedule(key, deadline),
+            ExpiryQueue::Wheel(wheel) => wheel.schedule(key, deadline)
+        }
+    }
+
+    pub(crate) fn cancel(&mut self, id: u64) {
+        match self {
+            ExpiryQueue::Heap(heap) => heap.cancel(id),
+            ExpiryQueue::Wheel(wheel) => wheel.cancel(id)
+        }
+    }
+
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<K> {
+        match self {
+            ExpiryQueue::Heap(heap) => heap.pop_due(now),
+            ExpiryQueue::Wheel(wheel) => wheel.pop_due(now)
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            ExpiryQueue::Heap(heap) => heap.clear(),
+            ExpiryQueue::Wheel(wheel) => wheel.clear()
+        }
+    }
+}
+
+// min-heap of entry deadlines, cancelled entries are dropped lazily when they come up,
+// or all at once when they outnumber the live ones two to one
+pub(crate) struct DeadlineHeap<K> {
+    heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    keys: HashMap<u64, K>,
+    next_id: u64
+}
+
+impl<K> DeadlineHeap<K> {
+    fn new() -> Self {
+        DeadlineHeap {
+            heap: BinaryHeap::new(),
+            keys: HashMap::new(),
+            next_id: 0
+        }
+    }
+
+    fn schedule(&mut self, key: K, deadline: Instant) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.heap.push(Reverse((deadline, id)));
+        self.keys.insert(id, key);
+        id
+    }
+
+    fn cancel(&mut self, id: u64) {
+        self.keys.remove(&id);
+        if self.heap.len() - self.keys.len() > 2 * self.keys.len() {
+            let keys = &self.keys;
+            self.heap.retain(|Reverse((_, id))| keys.contains_key(id));
+        }
+    }
+
+    // the next still scheduled key whose deadline is at or before `now`
+    fn pop_due(&mut self, now: Instant) -> Option<K> {
+        while let Some(Reverse((deadline, id))) = self.heap.peek().copied() {
+            if deadline > now {
+                return None;
+            }
+            self.heap.pop();
+            if let Some(key) = self.keys.remove(&id) {
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+        self.keys.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_queue_pops_due_in_order() {
+        let now = Instant::now();
+        let mut queue = ExpiryQueue::new(ExpiryBackend::Heap);
+        queue.schedule("late", now + Duration::from_secs(60));
+        queue.schedule("second", now + Duration::from_secs(2));
+        let cancelled = queue.schedule("cancelled", now);
+        queue.schedule("first", now + Duration::from_secs(1));
+        queue.cancel(cancelled);
+        let later = now + Duration::from_secs(5);
+        assert_eq!(queue.pop_due(later), Some("first"));
+        assert_eq!(queue.pop_due(later), Some("second"));
+        assert_eq!(queue.pop_due(later), None);
+    }
+
+    #[test]
+    fn test_deadline_heap_drops_cancelled_entries() {
+        let now = Instant::now();
+        let mut heap = DeadlineHeap::new();
+        let mut ids: Vec<u64> = (0..10).map(|key| heap.schedule(key, now + Duration::from_secs(60))).collect();
+        // refreshing reschedules and cancels the old deadline, over and over
+        for round in 0..10_000 {
+            let key = round % 10;
+            heap.cancel(ids[key]);
+            ids[key] = heap.schedule(key, now + Duration::from_secs(60 + round as u64));
+            assert!(heap.heap.len() <= 3 * heap.keys.len() + 1);
+        }
+        assert_eq!(heap.keys.len(), 10);
+        assert_eq!(heap.pop_due(now + Duration::from_secs(60 + 9_990)), Some(0));
+    }
+}
@@ -0,0 +1,93 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::slab::Slab;
+use crate::{LruCache, LruNode, RandomState};
+
+// an immutable LruCache, made by freeze. it is Sync, so request handlers can share one behind an Arc while
+// a builder prepares the next. lookups don't change recency, and thaw gives back the cache as it was frozen
+pub struct FrozenLruCache<K: Clone + Eq + Hash, V> {
+    map: HashMap<K, u32, RandomState>,
+    nodes: Slab<LruNode<K, V>>,
+    // least recently used first, as when frozen
+    order: Vec<K>,
+    // the cache without its entries, holding the settings for thaw. only ever locked there
+    settings: Mutex<LruCache<K, V>>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> FrozenLruCache<K, V> {
+    pub(crate) fn new(mut cache: LruCache<K, V>) -> Self {
+        let order = cache.keys_in_order();
+        let map = std::mem::take(&mut cache.map);
+        let nodes = std::mem::replace(&mut cache.nodes, Slab::new());
+        FrozenLruCache { map, nodes, order, settings: Mutex::new(cache) }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>
+    {
+        let node = self.nodes.get(*self.map.get(key)?)?;
+        if node.is_expired(Instant::now()) {
+            return None;
+        }
+        Some(&node.value)
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.get(key).is_some()
+    }
+
+    // live entries, least recently used first
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.order.iter()
+            .filter_map(|key| Some((key.clone(), self.get(key)?.clone())))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn thaw(self) -> LruCache<K, V> {
+        let mut cache = self.settings.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        cache.map = self.map;
+        cache.nodes = self.nodes;
+        cache
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_freeze_and_thaw() {
+        let mut lru = LruCache::new(2).weigh_by(10, |_, value: &usize| *value);
+        lru.put(1, 4);
+        lru.put(2, 4);
+        lru.get(&1);
+
+        let frozen = Arc::new(lru.freeze());
+        let reader = Arc::clone(&frozen);
+        assert_eq!(thread::spawn(move || reader.get(&2).copied()).join().unwrap(), Some(4));
+        assert_eq!(frozen.get(&3), None);
+        assert_eq!(frozen.entries(), vec![(2, 4), (1, 4)]);
+
+        let mut lru = Arc::into_inner(frozen).unwrap().thaw();
+        assert_eq!(lru.current_weight(), 8);
+        assert_eq!(lru.push(3, 4), vec![(2, 4)]);
+    }
+}
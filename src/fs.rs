@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Cache, LruCache};
+
+const INDEX_FILE: &str = "index";
+
+// a persistent cache keeping one file per entry under a directory, pruned lru first by total size.
+// recency survives restarts through an index file written on flush and on drop. files are named by key hash and
+// start with their key, so a colliding key's file is never read or removed in place of this one's
+pub struct FsCache {
+    dir: PathBuf,
+    index: LruCache<String, u64>
+}
+
+impl FsCache {
+    pub fn open<P: AsRef<Path>>(dir: P, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut cache = FsCache {
+            dir,
+            index: LruCache::new(usize::MAX).weigh_by(max_bytes as usize, |_, size: &u64| *size as usize)
+        };
+
+        let index = match fs::read_to_string(cache.dir.join(INDEX_FILE)) {
+            Ok(index) => index,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error)
+        };
+        let indexed: Vec<(String, u64)> = index.lines()
+            .filter_map(|line| line.split_once(' ').and_then(|(key, size)| Some((decode(key)?, size.parse().ok()?))))
+            .collect();
+
+        // files the index doesn't list (written after the last flush, or by a crashed process) still take up
+        // room, they go in first as the least recently used
+        let listed: HashSet<PathBuf> = indexed.iter().map(|(key, _)| cache.path(key)).collect();
+        for entry in fs::read_dir(&cache.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "tmp") {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if path.file_name().is_some_and(|name| name == INDEX_FILE) || listed.contains(&path) {
+                continue;
+            }
+            match stored_key(&path) {
+                Some((key, header)) if cache.path(&key) == path => {
+                    let size = fs::metadata(&path)?.len().saturating_sub(header);
+                    cache.record(key, size);
+                },
+                // named by another hash, e.g. by an older version: moved to its name unless that is taken
+                Some((key, header)) if !cache.path(&key).exists() => {
+                    let size = fs::metadata(&path)?.len().saturating_sub(header);
+                    fs::rename(&path, cache.path(&key))?;
+                    cache.record(key, size);
+                },
+                _ => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        for (key, size) in indexed {
+            if cache.owns(&key) {
+                cache.record(key, size);
+            }
+        }
+        Ok(cache)
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.index.get(&key.to_string())?;
+        match self.read(key) {
+            Some(value) => Some(value),
+            None => {
+                self.index.delete(&key.to_string());
+                None
+            }
+        }
+    }
+
+    pub fn put(&mut self, key: &str, value: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        // a colliding key's file is about to be replaced, so that key is gone
+        if let Some((other, _)) = stored_key(&path).filter(|(other, _)| other != key) {
+            self.index.delete(&other);
+        }
+        let staging = path.with_extension("tmp");
+        let mut file = fs::File::create(&staging)?;
+        file.write_all(&(key.len() as u32).to_be_bytes())?;
+        file.write_all(key.as_bytes())?;
+        file.write_all(value)?;
+        drop(file);
+        fs::rename(staging, path)?;
+        self.record(key.to_string(), value.len() as u64);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, key: &str) -> io::Result<()> {
+        if self.index.remove(&key.to_string()).is_some() {
+            self.remove_file(key)?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.index.current_weight() as u64
+    }
+
+    pub fn reset(&mut self) -> io::Result<()> {
+        while let Some((key, _)) = self.index.pop_lru() {
+            self.remove_file(&key)?;
+        }
+        self.flush()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        let mut index = String::new();
+        for (key, size) in self.index.entries() {
+            index.push_str(&format!("{} {}\n", encode(&key), size));
+        }
+        let staging = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&staging, index)?;
+        fs::rename(staging, self.dir.join(INDEX_FILE))
+    }
+
+    fn record(&mut self, key: String, size: u64) {
+        for (evicted, _) in self.index.push(key, size) {
+            let _ = self.remove_file(&evicted);
+        }
+    }
+
+    fn owns(&self, key: &str) -> bool {
+        stored_key(&self.path(key)).is_some_and(|(stored, _)| stored == key)
+    }
+
+    // leaves the file alone if it holds another key's value
+    fn remove_file(&self, key: &str) -> io::Result<()> {
+        if self.owns(key) {
+            fs::remove_file(self.path(key))?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        let stored = fs::read(self.path(key)).ok()?;
+        let key_len = u32::from_be_bytes(stored.get(..4)?.try_into().ok()?) as usize;
+        // files are named by key hash, make sure this one isn't a colliding key's
+        if stored.get(4..4 + key_len)? != key.as_bytes() {
+            return None;
+        }
+        Some(stored[4 + key_len..].to_vec())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+}
+
+impl Drop for FsCache {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// file names have to stay the same across builds, which DefaultHasher doesn't promise
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// the key a file was written for, and the length of the header holding it
+fn stored_key(path: &Path) -> Option<(String, u64)> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut key_len = [0; 4];
+    file.read_exact(&mut key_len).ok()?;
+    let mut key = vec![0; u32::from_be_bytes(key_len) as usize];
+    file.read_exact(&mut key).ok()?;
+    let header = 4 + key.len() as u64;
+    Some((String::from_utf8(key).ok()?, header))
+}
+
+fn encode(key: &str) -> String {
+    key.bytes().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode(encoded: &str) -> Option<String> {
+    let bytes = (0..encoded.len()).step_by(2)
+        .map(|at| u8::from_str_radix(encoded.get(at..at + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+// io errors read as misses and are dropped on writes
+impl Cache<String, Vec<u8>> for FsCache {
+    fn get(&mut self, key: &String) -> Option<Vec<u8>> {
+        FsCache::get(self, key)
+    }
+
+    fn push(&mut self, key: String, value: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+        let _ = FsCache::put(self, &key, &value);
+        Vec::new()
+    }
+
+    fn delete(&mut self, key: &String) {
+        let _ = FsCache::delete(self, key);
+    }
+
+    fn reset(&mut self) {
+        let _ = FsCache::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fs_cache_prunes_and_persists() {
+        let dir = std::env::temp_dir().join(format!("lru-fs-{}", std::process::id()));
+        {
+            let mut cache = FsCache::open(&dir, 10).unwrap();
+            cache.put("a", b"1234").unwrap();
+            cache.put("b", b"1234").unwrap();
+            cache.get("a");
+            cache.put("c", b"1234").unwrap();
+            assert_eq!(cache.get("b"), None);
+            assert_eq!(cache.size(), 8);
+        }
+
+        let mut cache = FsCache::open(&dir, 10).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("a"), Some(b"1234".to_vec()));
+        cache.put("d", b"12").unwrap();
+        cache.put("e", b"12").unwrap();
+        assert_eq!(cache.get("c"), None);
+        cache.reset().unwrap();
+        assert!(cache.is_empty());
+        drop(cache);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fs_cache_counts_unindexed_files() {
+        let dir = std::env::temp_dir().join(format!("lru-fs-orphans-{}", std::process::id()));
+        let mut cache = FsCache::open(&dir, 10).unwrap();
+        cache.put("a", b"1234").unwrap();
+        cache.put("b", b"1234").unwrap();
+        // as if the process died before flushing
+        std::mem::forget(cache);
+        fs::write(dir.join("stray.tmp"), b"partial").unwrap();
+
+        let mut cache = FsCache::open(&dir, 10).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 8);
+        assert!(!dir.join("stray.tmp").exists());
+        cache.put("c", b"1234").unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        drop(cache);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fs_cache_leaves_colliding_files() {
+        let dir = std::env::temp_dir().join(format!("lru-fs-collision-{}", std::process::id()));
+        let mut cache = FsCache::open(&dir, 100).unwrap();
+        cache.put("a", b"1").unwrap();
+        // another key's file under a's name, as a hash collision would leave it
+        let path = cache.path("a");
+        fs::write(&path, [&1u32.to_be_bytes()[..], b"b2"].concat()).unwrap();
+        cache.delete("a").unwrap();
+        assert!(path.exists());
+        assert_eq!(cache.get("a"), None);
+        drop(cache);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fs_cache_rehomes_misnamed_files() {
+        let dir = std::env::temp_dir().join(format!("lru-fs-misnamed-{}", std::process::id()));
+        let mut cache = FsCache::open(&dir, 100).unwrap();
+        cache.put("a", b"1234").unwrap();
+        cache.put("b", b"1234").unwrap();
+        cache.flush().unwrap();
+        // as if written by a build that hashed names differently, plus a duplicate and some junk
+        fs::rename(cache.path("a"), dir.join("0000000000000001")).unwrap();
+        fs::copy(cache.path("b"), dir.join("0000000000000002")).unwrap();
+        fs::write(dir.join("0000000000000003"), b"junk").unwrap();
+        std::mem::forget(cache);
+
+        let mut cache = FsCache::open(&dir, 100).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 8);
+        assert_eq!(cache.get("a"), Some(b"1234".to_vec()));
+        // the two cache files and the index
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        drop(cache);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fs_cache_names_are_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
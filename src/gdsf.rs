@@ -0,0 +1,238 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::Cache;
+
+type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize + Send + Sync>;
+
+struct Entry<V> {
+    value: V,
+    weight: usize,
+    frequency: u64,
+    // rank in `order`, kept to find the entry there again
+    rank: (u64, u64)
+}
+
+// GDSF (greedy dual size frequency): each entry's priority is `age + frequency / weight` and the lowest goes first,
+// so small, often used entries outlive big or rarely used ones. `age` rises to the priority of every evicted entry,
+// letting new arrivals compete with entries that built up frequency long ago. for object and web caches where
+// weights vary a lot, it holds more useful entries in the same budget than lru
+pub struct GdsfCache<K: Clone + Eq + Hash, V> {
+    max_weight: usize,
+    weight: usize,
+    weigher: Weigher<K, V>,
+    age: f64,
+    seq: u64,
+    entries: HashMap<K, Entry<V>>,
+    // (priority bits, insertion sequence) of every entry, the first is the victim.
+    // priorities are never negative, so their bits sort like the floats
+    order: BTreeMap<(u64, u64), K>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> GdsfCache<K, V> {
+    // entries weighing 0 count as 1
+    pub fn new<F>(max_weight: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + Sync + 'static
+    {
+        GdsfCache {
+            max_weight,
+            weight: 0,
+            weigher: Box::new(weigher),
+            age: 0.0,
+            seq: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new()
+        }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let (key, entry) = self.entries.get_key_value(key)?;
+        let (key, value) = (key.clone(), entry.value.clone());
+        self.reference(&key);
+        Some(value)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.entries.get(key).map(|entry| entry.value.clone())
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entries evicted to make room, or the entry itself if it outweighs the whole budget
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let weight = (self.weigher)(&key, &value).max(1);
+        if weight > self.max_weight {
+            self.remove(&key);
+            return vec![(key, value)];
+        }
+
+        let frequency = match self.entries.remove(&key) {
+            None => 0,
+            Some(entry) => {
+                self.order.remove(&entry.rank);
+                self.weight -= entry.weight;
+                entry.frequency
+            }
+        };
+        let mut evicted = Vec::new();
+        while self.weight + weight > self.max_weight {
+            match self.evict() {
+                None => break,
+                Some(entry) => evicted.push(entry)
+            }
+        }
+        self.weight += weight;
+        self.entries.insert(key.clone(), Entry { value, weight, frequency, rank: (0, 0) });
+        self.reference(&key);
+        evicted
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.rank);
+        self.weight -= entry.weight;
+        Some(entry.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn current_weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn max_weight(&self) -> usize {
+        self.max_weight
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.weight = 0;
+        self.age = 0.0;
+    }
+
+    fn reference(&mut self, key: &K) {
+        let Some(entry) = self.entries.get_mut(key) else { return };
+        self.order.remove(&entry.rank);
+        entry.frequency += 1;
+        let priority = self.age + entry.frequency as f64 / entry.weight as f64;
+        self.seq += 1;
+        entry.rank = (priority.to_bits(), self.seq);
+        self.order.insert(entry.rank, key.clone());
+    }
+
+    fn evict(&mut self) -> Option<(K, V)> {
+        let ((priority, _), key) = self.order.pop_first()?;
+        self.age = f64::from_bits(priority);
+        let entry = self.entries.remove(&key)?;
+        self.weight -= entry.weight;
+        Some((key, entry.value))
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for GdsfCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        GdsfCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        GdsfCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        GdsfCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        GdsfCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::LruCache;
+
+    fn size(_: &u32, size: &usize) -> usize {
+        *size
+    }
+
+    fn hits<C: Cache<u32, usize>>(cache: &mut C, trace: &[(u32, usize)]) -> usize {
+        trace.iter().filter(|&&(key, weight)| {
+            let hit = cache.get(&key).is_some();
+            if !hit {
+                cache.put(key, weight);
+            }
+            hit
+        }).count()
+    }
+
+    #[test]
+    fn test_gdsf_cache() {
+        let mut cache = GdsfCache::new(10, size);
+        cache.put(1, 2);
+        cache.put(2, 6);
+        cache.get(&1);
+        assert_eq!(cache.push(3, 4), vec![(2, 6)]);
+        assert_eq!(cache.current_weight(), 6);
+        assert_eq!(cache.push(4, 11), vec![(4, 11)]);
+        assert_eq!(cache.remove(&3), Some(4));
+        assert_eq!(cache.get(&1), Some(2));
+        cache.reset();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_gdsf_prefers_small_entries() {
+        // small keys read over and over, big one-off entries in between
+        let trace: Vec<(u32, usize)> = (0..50u32).flat_map(|pass| (0..8).map(|key| (key, 1)).chain([(1000 + pass, 20)])).collect();
+        let lru = hits(&mut LruCache::new(usize::MAX).weigh_by(24, size), &trace);
+        let gdsf = hits(&mut GdsfCache::new(24, size), &trace);
+        assert!(gdsf > lru * 2, "{} hits against {} for lru", gdsf, lru);
+    }
+
+    proptest! {
+        #[test]
+        fn test_gdsf_stays_within_budget(max_weight in 1..30usize, ops in proptest::collection::vec((0..3u8, 0..16u32, 0..10usize), 0..300)) {
+            let mut cache = GdsfCache::new(max_weight, size);
+            for (op, key, weight) in ops {
+                match op {
+                    0 => { cache.get(&key); },
+                    1 => cache.put(key, weight),
+                    _ => cache.delete(&key)
+                }
+                prop_assert!(cache.current_weight() <= max_weight);
+                prop_assert_eq!(cache.order.len(), cache.len());
+                prop_assert_eq!(cache.entries.values().map(|entry| entry.weight).sum::<usize>(), cache.current_weight());
+            }
+        }
+    }
+}
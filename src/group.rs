@@ -0,0 +1,212 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Instant;
+
+use crate::{Cache, LruCache};
+
+// what the group needs from a member, whatever its key and value types
+trait Member: Send + Sync {
+    fn weight(&self) -> usize;
+
+    // when the member's least recently used entry was last read or written
+    fn oldest_use(&self) -> Option<Instant>;
+
+    fn evict_oldest(&self) -> bool;
+}
+
+impl<K, V> Member for Mutex<LruCache<K, V>>
+where
+    K: Clone + Eq + Hash + Send,
+    V: Clone + Send
+{
+    fn weight(&self) -> usize {
+        self.lock().unwrap().current_weight()
+    }
+
+    fn oldest_use(&self) -> Option<Instant> {
+        self.lock().unwrap().oldest_use()
+    }
+
+    fn evict_oldest(&self) -> bool {
+        self.lock().unwrap().pop_lru().is_some()
+    }
+}
+
+struct GroupState {
+    budget: usize,
+    members: Vec<Weak<dyn Member>>
+}
+
+// a weight budget shared by several caches, possibly of different types. whenever a member grows past it
+// the least recently used entry across all members is evicted, until the members fit again
+#[derive(Clone)]
+pub struct CacheGroup {
+    state: Arc<Mutex<GroupState>>
+}
+
+impl CacheGroup {
+    pub fn new(budget: usize) -> Self {
+        CacheGroup { state: Arc::new(Mutex::new(GroupState { budget, members: Vec::new() })) }
+    }
+
+    // the cache keeps its own capacity and weigher, its weight counts against the group's budget too
+    pub fn register<K, V>(&self, cache: LruCache<K, V>) -> GroupedCache<K, V>
+    where
+        K: Clone + Eq + Hash + Send + 'static,
+        V: Clone + Send + 'static
+    {
+        let cache = Arc::new(Mutex::new(cache));
+        let member: Arc<dyn Member> = cache.clone();
+        self.state.lock().unwrap().members.push(Arc::downgrade(&member));
+        GroupedCache { cache, group: self.clone() }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.state.lock().unwrap().budget
+    }
+
+    // evicts right away if the members no longer fit
+    pub fn set_budget(&self, budget: usize) {
+        self.state.lock().unwrap().budget = budget;
+        self.enforce();
+    }
+
+    // combined weight of the live members
+    pub fn current_weight(&self) -> usize {
+        self.members().iter().map(|member| member.weight()).sum()
+    }
+
+    fn members(&self) -> Vec<Arc<dyn Member>> {
+        let mut state = self.state.lock().unwrap();
+        state.members.retain(|member| member.strong_count() > 0);
+        state.members.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    // called without any member locked, members are only locked one at a time
+    fn enforce(&self) {
+        let budget = self.budget();
+        let members = self.members();
+        let mut weight: usize = members.iter().map(|member| member.weight()).sum();
+        while weight > budget {
+            let oldest = members.iter()
+                .filter_map(|member| Some((member.oldest_use()?, member)))
+                .min_by_key(|(used, _)| *used);
+            let Some((_, member)) = oldest else { break };
+            let before = member.weight();
+            if !member.evict_oldest() {
+                break;
+            }
+            weight = weight.saturating_sub(before.saturating_sub(member.weight()));
+        }
+    }
+}
+
+// a thread safe cache whose weight counts against a CacheGroup's budget
+pub struct GroupedCache<K: Clone + Eq + Hash, V> {
+    cache: Arc<Mutex<LruCache<K, V>>>,
+    group: CacheGroup
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> GroupedCache<K, V> {
+    pub fn get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.lock().unwrap().get(key)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.lock().unwrap().peek(key)
+    }
+
+    pub fn put(&self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entries this cache itself evicted, entries the group evicts to stay in budget are dropped
+    pub fn push(&self, key: K, value: V) -> Vec<(K, V)> {
+        let evicted = self.cache.lock().unwrap().push(key, value);
+        self.group.enforce();
+        evicted
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.cache.lock().unwrap().delete(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.lock().unwrap().remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.lock().unwrap().is_empty()
+    }
+
+    pub fn current_weight(&self) -> usize {
+        self.cache.lock().unwrap().current_weight()
+    }
+
+    pub fn reset(&self) {
+        self.cache.lock().unwrap().reset();
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for GroupedCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        GroupedCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        GroupedCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        GroupedCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        GroupedCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_group_shares_budget() {
+        let group = CacheGroup::new(10);
+        let users = group.register(LruCache::new(100).weigh_by(100, |_, name: &String| name.len()));
+        let counts = group.register(LruCache::new(100));
+        users.put(1, "alice".to_string());
+        counts.put("a", 1);
+        counts.put("b", 2);
+        users.get(&1);
+        assert_eq!(group.current_weight(), 7);
+
+        users.put(2, "bobby".to_string());
+        assert_eq!(group.current_weight(), 10);
+        assert_eq!(counts.len(), 0);
+        counts.put("c", 3);
+        assert_eq!(users.peek(&1), None);
+        assert_eq!(users.peek(&2), Some("bobby".to_string()));
+
+        drop(counts);
+        group.set_budget(4);
+        assert!(users.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::slab::Slab;
+
+// counters with the same count, linked in ascending count order
+struct Bucket {
+    count: u64,
+    counters: Vec<usize>,
+    prev: Option<u32>,
+    next: Option<u32>
+}
+
+struct Counter<K> {
+    key: K,
+    bucket: u32,
+    // where it sits in its bucket's `counters`
+    at: usize
+}
+
+// the space-saving algorithm: tracks the most frequent keys of a stream in `slots` counters.
+// a new key takes over the smallest counter, so counts can overestimate by at most that counter's value.
+// counters are kept in a stream summary, buckets of equal counts in count order, so a hit moves its counter one
+// bucket up and the smallest counter is always in the first bucket, both in constant time
+pub struct SpaceSaving<K> {
+    slots: usize,
+    index: HashMap<K, usize>,
+    counters: Vec<Counter<K>>,
+    buckets: Slab<Bucket>,
+    // the bucket with the smallest count
+    head: Option<u32>
+}
+
+impl<K: Clone + Eq + Hash> SpaceSaving<K> {
+    pub fn new(slots: usize) -> Self {
+        SpaceSaving {
+            slots: slots.max(1),
+            index: HashMap::new(),
+            counters: Vec::new(),
+            buckets: Slab::new(),
+            head: None
+        }
+    }
+
+    // takes any borrowed form of the key, it is only turned into an owned key when it starts being tracked
+    pub fn record<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        if let Some(&counter) = self.index.get(key) {
+            self.increment(counter);
+            return;
+        }
+
+        if self.counters.len() < self.slots {
+            let counter = self.counters.len();
+            let bucket = match self.head {
+                Some(head) if self.buckets[head].count == 1 => head,
+                head => {
+                    let bucket = self.buckets.insert(Bucket { count: 1, counters: Vec::new(), prev: None, next: head });
+                    if let Some(head) = head {
+                        self.buckets[head].prev = Some(bucket);
+                    }
+                    self.head = Some(bucket);
+                    bucket
+                }
+            };
+            self.counters.push(Counter { key: key.to_owned(), bucket, at: self.buckets[bucket].counters.len() });
+            self.buckets[bucket].counters.push(counter);
+            self.index.insert(key.to_owned(), counter);
+            return;
+        }
+
+        // the first bucket is never empty, its last counter is evicted and counts on from the smallest count
+        let Some(head) = self.head else { return };
+        let Some(&counter) = self.buckets[head].counters.last() else { return };
+        let evicted = std::mem::replace(&mut self.counters[counter].key, key.to_owned());
+        self.index.remove::<K>(&evicted);
+        self.index.insert(key.to_owned(), counter);
+        self.increment(counter);
+    }
+
+    // the `n` most frequent keys with their estimated counts, most frequent first
+    pub fn top(&self, n: usize) -> Vec<(K, u64)> {
+        let mut top: Vec<(K, u64)> = self.counters.iter()
+            .map(|counter| (counter.key.clone(), self.buckets[counter.bucket].count))
+            .collect();
+        top.sort_by_key(|(_, count)| Reverse(*count));
+        top.truncate(n);
+        top
+    }
+
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.counters.clear();
+        self.buckets.clear();
+        self.head = None;
+    }
+
+    // moves a counter into the bucket one count up, making it if needed, and drops the bucket it left if empty
+    fn increment(&mut self, counter: usize) {
+        let (from, at) = (self.counters[counter].bucket, self.counters[counter].at);
+        let count = self.buckets[from].count + 1;
+        let next = self.buckets[from].next;
+        let to = match next {
+            Some(next) if self.buckets[next].count == count => next,
+            _ => {
+                let bucket = self.buckets.insert(Bucket { count, counters: Vec::new(), prev: Some(from), next });
+                if let Some(next) = next {
+                    self.buckets[next].prev = Some(bucket);
+                }
+                self.buckets[from].next = Some(bucket);
+                bucket
+            }
+        };
+
+        let bucket = &mut self.buckets[from];
+        bucket.counters.swap_remove(at);
+        if let Some(&moved) = bucket.counters.get(at) {
+            self.counters[moved].at = at;
+        }
+        self.counters[counter].bucket = to;
+        self.counters[counter].at = self.buckets[to].counters.len();
+        self.buckets[to].counters.push(counter);
+
+        if self.buckets[from].counters.is_empty() {
+            self.unlink(from);
+        }
+    }
+
+    fn unlink(&mut self, bucket: u32) {
+        let Some(Bucket { prev, next, .. }) = self.buckets.remove(bucket) else { return };
+        match prev {
+            Some(prev) => self.buckets[prev].next = next,
+            None => self.head = next
+        }
+        if let Some(next) = next {
+            self.buckets[next].prev = prev;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_saving() {
+        let mut tracker = SpaceSaving::new(3);
+        for key in [1, 1, 1, 1, 2, 2, 2, 3, 4] {
+            tracker.record(&key);
+        }
+        let top = tracker.top(2);
+        assert_eq!(top[0], (1, 4));
+        assert_eq!(top[1], (2, 3));
+        // 4 took over 3's counter of 1
+        assert_eq!(tracker.top(3)[2], (4, 2));
+    }
+
+    #[test]
+    fn test_space_saving_buckets_stay_ordered() {
+        let mut tracker = SpaceSaving::new(4);
+        for round in 0..50u64 {
+            tracker.record(&(round % 7));
+            tracker.record(&0);
+        }
+        let mut counts = Vec::new();
+        let mut bucket = tracker.head;
+        while let Some(slot) = bucket {
+            let Bucket { count, counters, next, .. } = &tracker.buckets[slot];
+            assert!(!counters.is_empty());
+            for &counter in counters {
+                assert_eq!(tracker.counters[counter].bucket, slot);
+            }
+            counts.push(*count);
+            bucket = *next;
+        }
+        assert!(counts.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(tracker.buckets.len(), counts.len());
+        assert_eq!(tracker.top(1), vec![(0, 58)]);
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use http::header::{AGE, AUTHORIZATION, CACHE_CONTROL, ETAG, EXPIRES, IF_NONE_MATCH, VARY};
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+
+use crate::{LruCache, Lookup};
+
+// longest a response is kept fresh whatever the origin says, a year like most shared caches
+const MAX_FRESHNESS: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+#[derive(Clone)]
+struct Stored {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Arc<[u8]>,
+    fresh_until: Instant
+}
+
+impl Stored {
+    fn response(&self) -> Response<Arc<[u8]>> {
+        let mut response = Response::new(self.body.clone());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+// caches GET and HEAD responses keyed on method and url, bounded by total body length.
+// freshness comes from Cache-Control max-age or Expires, stale entries with an ETag are revalidated.
+// the cache may be shared between users, so private responses and ones that Vary are never stored, nor are
+// answers to authorized requests unless the response allows it with public, s-maxage or must-revalidate
+pub struct HttpCache {
+    cache: LruCache<(Method, Uri), Stored>
+}
+
+impl HttpCache {
+    pub fn new(capacity: usize, max_bytes: usize) -> Self {
+        HttpCache {
+            cache: LruCache::new(capacity).weigh_by(max_bytes, |_, stored: &Stored| stored.body.len())
+        }
+    }
+
+    // stale responses are still returned so callers can fall back to them
+    pub fn lookup<B>(&mut self, request: &Request<B>) -> Lookup<Response<Arc<[u8]>>> {
+        match self.cache.get(&key(request)) {
+            Some(stored) if Instant::now() < stored.fresh_until => Lookup::Fresh(stored.response()),
+            Some(stored) => Lookup::Stale(stored.response()),
+            None => Lookup::Miss
+        }
+    }
+
+    // adds If-None-Match for a cached ETag so the origin can answer 304
+    pub fn prepare<B>(&mut self, request: &mut Request<B>) {
+        let etag = self.cache.peek(&key(request)).and_then(|stored| stored.headers.get(ETAG).cloned());
+        if let Some(etag) = etag {
+            request.headers_mut().insert(IF_NONE_MATCH, etag);
+        }
+    }
+
+    // records the origin's answer to `request` and returns the response to serve.
+    // a 304 refreshes the cached entry and serves its body
+    pub fn update<B, R: Into<Arc<[u8]>>>(&mut self, request: &Request<B>, response: Response<R>) -> Response<Arc<[u8]>> {
+        let key = key(request);
+        let (parts, body) = response.into_parts();
+
+        if parts.status == StatusCode::NOT_MODIFIED {
+            if let Some(mut stored) = self.cache.remove(&key) {
+                for (name, value) in &parts.headers {
+                    stored.headers.insert(name, value.clone());
+                }
+                let response = stored.response();
+                if let Some((_, fresh_until)) = fresh_until(&stored.headers) {
+                    stored.fresh_until = fresh_until;
+                    self.cache.put(key, stored);
+                }
+                return response;
+            }
+        }
+
+        let response = Response::from_parts(parts, body.into());
+        match fresh_until(response.headers()) {
+            Some((ttl, fresh_until)) if cacheable(request, &response) && (!ttl.is_zero() || response.headers().contains_key(ETAG)) => {
+                self.cache.put(key, Stored {
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    body: response.body().clone(),
+                    fresh_until
+                });
+            }
+            _ => self.cache.delete(&key)
+        }
+        response
+    }
+
+    pub fn delete(&mut self, method: &Method, uri: &Uri) {
+        self.cache.delete(&(method.clone(), uri.clone()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn current_bytes(&self) -> usize {
+        self.cache.current_weight()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+    }
+}
+
+fn key<B>(request: &Request<B>) -> (Method, Uri) {
+    (request.method().clone(), request.uri().clone())
+}
+
+// a response that Vary's would need the request headers in the key, it is refused instead
+fn cacheable<B, R>(request: &Request<B>, response: &Response<R>) -> bool {
+    let method = request.method();
+    (method == Method::GET || method == Method::HEAD)
+        && response.status() == StatusCode::OK
+        && !response.headers().contains_key(VARY)
+        && (!request.headers().contains_key(AUTHORIZATION) || shareable(response.headers()))
+}
+
+// whether a response to an authorized request may be stored in a shared cache
+fn shareable(headers: &HeaderMap) -> bool {
+    directives(headers).any(|directive| {
+        directive == "public" || directive == "must-revalidate" || directive.split_once('=').is_some_and(|(name, _)| name == "s-maxage")
+    })
+}
+
+fn directives(headers: &HeaderMap) -> impl Iterator<Item = String> + '_ {
+    headers.get_all(CACHE_CONTROL).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+}
+
+// the freshness and the deadline it gives from now, None when the response must not be stored
+fn fresh_until(headers: &HeaderMap) -> Option<(Duration, Instant)> {
+    let ttl = freshness(headers)?;
+    Some((ttl, Instant::now().checked_add(ttl)?))
+}
+
+// how long a response stays fresh, at most MAX_FRESHNESS. None when it must not be stored
+fn freshness(headers: &HeaderMap) -> Option<Duration> {
+    // every directive is read first, no-store and private win over no-cache wherever they appear
+    let mut max_age = None;
+    let mut no_cache = false;
+    for directive in directives(headers) {
+        match directive.split_once('=') {
+            _ if directive == "no-store" || directive == "private" => return None,
+            _ if directive == "no-cache" => no_cache = true,
+            Some(("max-age", seconds)) => max_age = seconds.trim_matches('"').parse().ok(),
+            _ => ()
+        }
+    }
+    if no_cache {
+        return Some(Duration::ZERO);
+    }
+
+    if let Some(max_age) = max_age {
+        let age = headers.get(AGE).and_then(|age| age.to_str().ok()?.parse().ok()).unwrap_or(0);
+        return Some(Duration::from_secs(u64::saturating_sub(max_age, age)).min(MAX_FRESHNESS));
+    }
+    // an unparseable Expires means already expired
+    let expires = headers.get(EXPIRES).map(|expires| {
+        expires.to_str().ok()
+            .and_then(|expires| httpdate::parse_http_date(expires).ok())
+            .and_then(|expires| expires.duration_since(SystemTime::now()).ok())
+            .unwrap_or_default()
+    });
+    Some(expires.unwrap_or_default().min(MAX_FRESHNESS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(url: &str) -> Request<()> {
+        Request::get(url).body(()).unwrap()
+    }
+
+    #[test]
+    fn test_http_cache_freshness() {
+        let mut cache = HttpCache::new(10, 1024);
+        let request = get("http://example.com/a");
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+
+        let response = Response::builder().header(CACHE_CONTROL, "public, max-age=60").body(b"hello".to_vec()).unwrap();
+        cache.update(&request, response);
+        match cache.lookup(&request) {
+            Lookup::Fresh(response) => assert_eq!(&response.body()[..], b"hello"),
+            _ => panic!("expected a fresh hit")
+        }
+
+        let request = get("http://example.com/b");
+        let response = Response::builder().header(CACHE_CONTROL, "no-store").body(b"secret".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+        assert_eq!(cache.current_bytes(), 5);
+    }
+
+    #[test]
+    fn test_http_cache_refuses_private_and_vary() {
+        let mut cache = HttpCache::new(10, 1024);
+        let request = get("http://example.com/huge");
+        let response = Response::builder().header(CACHE_CONTROL, "max-age=99999999999999999").body(b"a".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Fresh(_)));
+
+        let request = get("http://example.com/me");
+        let response = Response::builder().header(CACHE_CONTROL, "private, max-age=60").body(b"mine".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+
+        let request = get("http://example.com/page");
+        let response = Response::builder().header(CACHE_CONTROL, "max-age=60").header(VARY, "Cookie").body(b"page".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_http_cache_revalidates() {
+        let mut cache = HttpCache::new(10, 1024);
+        let mut request = get("http://example.com/a");
+        let response = Response::builder().header(CACHE_CONTROL, "no-cache").header(ETAG, "\"v1\"").body(b"hello".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(cache.lookup(&request).is_stale());
+
+        cache.prepare(&mut request);
+        assert_eq!(request.headers()[IF_NONE_MATCH], "\"v1\"");
+        let not_modified = Response::builder().status(StatusCode::NOT_MODIFIED).header(CACHE_CONTROL, "max-age=60").body(Vec::new()).unwrap();
+        let response = cache.update(&request, not_modified);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&response.body()[..], b"hello");
+        assert!(matches!(cache.lookup(&request), Lookup::Fresh(_)));
+    }
+
+    #[test]
+    fn test_http_cache_no_store_beats_no_cache() {
+        let mut cache = HttpCache::new(10, 1024);
+        let request = get("http://example.com/a");
+        let response = Response::builder().header(CACHE_CONTROL, "no-cache, no-store").header(ETAG, "\"v1\"").body(b"secret".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_http_cache_authorized_requests() {
+        let mut cache = HttpCache::new(10, 1024);
+        let request = Request::get("http://example.com/me").header(AUTHORIZATION, "Bearer token").body(()).unwrap();
+        let response = Response::builder().header(CACHE_CONTROL, "max-age=60").body(b"mine".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Miss));
+
+        let response = Response::builder().header(CACHE_CONTROL, "max-age=60, s-maxage=60").body(b"ours".to_vec()).unwrap();
+        cache.update(&request, response);
+        assert!(matches!(cache.lookup(&request), Lookup::Fresh(_)));
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+type Extractor<V> = Box<dyn Fn(&V) -> u64 + Send + Sync>;
+
+// keys grouped by a value derived index key. index keys are kept as 64 bit hashes so the cache
+// doesn't need another type parameter, a collision only costs a few extra invalidated entries
+pub(crate) struct ValueIndex<K, V> {
+    extract: Extractor<V>,
+    hasher: RandomState,
+    keys: HashMap<u64, HashSet<K>>
+}
+
+impl<K: Clone + Eq + Hash, V> ValueIndex<K, V> {
+    pub(crate) fn new<I, F>(extract: F) -> Self
+    where
+        I: Hash,
+        F: Fn(&V) -> I + Send + Sync + 'static
+    {
+        let hasher = RandomState::new();
+        let index_hasher = hasher.clone();
+        ValueIndex {
+            extract: Box::new(move |value| index_hasher.hash_one(extract(value))),
+            hasher,
+            keys: HashMap::new()
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: &K, value: &V) {
+        self.keys.entry((self.extract)(value)).or_default().insert(key.clone());
+    }
+
+    pub(crate) fn remove(&mut self, key: &K, value: &V) {
+        let hash = (self.extract)(value);
+        if let Some(keys) = self.keys.get_mut(&hash) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.keys.remove(&hash);
+            }
+        }
+    }
+
+    pub(crate) fn take<I: Hash + ?Sized>(&mut self, index: &I) -> HashSet<K> {
+        self.keys.remove(&self.hasher.hash_one(index)).unwrap_or_default()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
@@ -0,0 +1,10 @@
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub inserted_at: Instant,
+    pub last_accessed: Instant,
+    pub access_count: u64,
+    pub weight: usize,
+    pub expires_at: Option<Instant>
+}
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::Cache;
+
+// carries invalidated keys between caches, the transport is up to the implementation
+pub trait InvalidationBus<K> {
+    fn publish(&self, key: &K);
+
+    // keys invalidated elsewhere since the last poll
+    fn poll(&self) -> Vec<K>;
+}
+
+// wraps a cache so local writes and deletes are published on the bus,
+// and invalidations from the bus are applied before every operation
+pub struct Coherent<C, B> {
+    cache: C,
+    bus: B
+}
+
+impl<C, B> Coherent<C, B> {
+    pub fn new(cache: C, bus: B) -> Self {
+        Coherent { cache, bus }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.cache
+    }
+
+    pub fn into_inner(self) -> C {
+        self.cache
+    }
+
+    // applies pending remote invalidations, returns how many keys were received
+    pub fn sync<K, V>(&mut self) -> usize
+    where
+        C: Cache<K, V>,
+        B: InvalidationBus<K>
+    {
+        let keys = self.bus.poll();
+        let count = keys.len();
+        for key in keys {
+            self.cache.delete(&key);
+        }
+        count
+    }
+}
+
+impl<K, V, C, B> Cache<K, V> for Coherent<C, B>
+where
+    C: Cache<K, V>,
+    B: InvalidationBus<K>
+{
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.sync();
+        self.cache.get(key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.sync();
+        self.bus.publish(&key);
+        self.cache.push(key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        self.sync();
+        self.bus.publish(key);
+        self.cache.delete(key)
+    }
+
+    fn reset(&mut self) {
+        self.cache.reset()
+    }
+}
+
+type Queue<K> = Arc<Mutex<VecDeque<K>>>;
+
+// an in process bus, every member receives what the other members publish
+pub struct MemoryBus<K> {
+    queues: Arc<Mutex<Vec<Queue<K>>>>
+}
+
+impl<K> Default for MemoryBus<K> {
+    fn default() -> Self {
+        MemoryBus { queues: Arc::new(Mutex::new(Vec::new())) }
+    }
+}
+
+impl<K> MemoryBus<K> {
+    pub fn new() -> Self {
+        MemoryBus::default()
+    }
+
+    pub fn member(&self) -> MemoryBusMember<K> {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        self.queues.lock().unwrap().push(Arc::clone(&queue));
+        MemoryBusMember {
+            queues: Arc::clone(&self.queues),
+            queue
+        }
+    }
+}
+
+pub struct MemoryBusMember<K> {
+    queues: Arc<Mutex<Vec<Queue<K>>>>,
+    queue: Queue<K>
+}
+
+impl<K: Clone> InvalidationBus<K> for MemoryBusMember<K> {
+    fn publish(&self, key: &K) {
+        for queue in self.queues.lock().unwrap().iter() {
+            if !Arc::ptr_eq(queue, &self.queue) {
+                queue.lock().unwrap().push_back(key.clone());
+            }
+        }
+    }
+
+    fn poll(&self) -> Vec<K> {
+        self.queue.lock().unwrap().drain(..).collect()
+    }
+}
+
+// unregisters the member, so publishers stop queueing keys nobody will poll
+impl<K> Drop for MemoryBusMember<K> {
+    fn drop(&mut self) {
+        if let Ok(mut queues) = self.queues.lock() {
+            queues.retain(|queue| !Arc::ptr_eq(queue, &self.queue));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LruCache;
+
+    #[test]
+    fn test_coherent_caches_share_invalidations() {
+        let bus = MemoryBus::new();
+        let mut first = Coherent::new(LruCache::new(10), bus.member());
+        let mut second = Coherent::new(LruCache::new(10), bus.member());
+        first.put(1, 1);
+        second.put(1, 2);
+        assert_eq!(first.get(&1), None);
+        assert_eq!(second.get(&1), Some(2));
+        second.put(2, 2);
+        first.put(2, 3);
+        first.delete(&2);
+        assert_eq!(second.get(&2), None);
+        assert_eq!(second.sync(), 0);
+    }
+
+    #[test]
+    fn test_memory_bus_forgets_dropped_members() {
+        let bus = MemoryBus::new();
+        let first = bus.member();
+        let second = bus.member();
+        let orphan = Arc::clone(&second.queue);
+        drop(second);
+        first.publish(&1);
+        assert!(orphan.lock().unwrap().is_empty());
+        assert_eq!(bus.queues.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+// longest key kept inline, so a SmallKey is 24 bytes like a String
+const INLINE: usize = 22;
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, bytes: [u8; INLINE] },
+    Heap(Arc<[u8]>)
+}
+
+// a string or byte key for LruCache. the cache clones a key into its map and its node, and into expiry
+// queues and indexes, which for a String is an allocation each. keys up to 22 bytes are stored inline and
+// clone with a copy, longer ones are shared and clone with a reference count bump
+#[derive(Clone)]
+pub struct SmallKey(Repr);
+
+impl SmallKey {
+    pub fn new(bytes: &[u8]) -> Self {
+        if bytes.len() > INLINE {
+            return SmallKey(Repr::Heap(Arc::from(bytes)));
+        }
+        let mut inline = [0; INLINE];
+        inline[..bytes.len()].copy_from_slice(bytes);
+        SmallKey(Repr::Inline { len: bytes.len() as u8, bytes: inline })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline { len, bytes } => &bytes[..*len as usize],
+            Repr::Heap(bytes) => bytes
+        }
+    }
+
+    // None if the key isn't valid utf-8
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl Deref for SmallKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+// hashes like the byte slice, so a SmallKey and its bytes find the same entry
+impl Borrow<[u8]> for SmallKey {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq for SmallKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for SmallKey {}
+
+impl Hash for SmallKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl PartialOrd for SmallKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl From<&str> for SmallKey {
+    fn from(key: &str) -> Self {
+        SmallKey::new(key.as_bytes())
+    }
+}
+
+impl From<&String> for SmallKey {
+    fn from(key: &String) -> Self {
+        SmallKey::new(key.as_bytes())
+    }
+}
+
+impl From<&[u8]> for SmallKey {
+    fn from(key: &[u8]) -> Self {
+        SmallKey::new(key)
+    }
+}
+
+impl fmt::Debug for SmallKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str() {
+            Some(key) => write!(f, "{:?}", key),
+            None => write!(f, "{:?}", self.as_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LruCache;
+
+    #[test]
+    fn test_small_key() {
+        assert_eq!(std::mem::size_of::<SmallKey>(), std::mem::size_of::<String>());
+        let short = SmallKey::from("user:42");
+        let long = SmallKey::from("session:0123456789abcdef0123456789");
+        assert!(short.is_inline());
+        assert!(!long.is_inline());
+        assert_eq!(short.as_str(), Some("user:42"));
+        assert_eq!(format!("{:?}", short), "\"user:42\"");
+
+        let mut lru = LruCache::new(2);
+        lru.put(short.clone(), 1);
+        lru.put(long.clone(), 2);
+        assert_eq!(lru.get(&SmallKey::from("user:42")), Some(1));
+        assert_eq!(lru.get(&long), Some(2));
+        assert!(lru.contains_key(&SmallKey::new(b"user:42")));
+    }
+}
@@ -0,0 +1,285 @@
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::{Buf, Bytes};
+use http::response::Parts;
+use http::{HeaderMap, Request, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use http_body_util::BodyExt;
+use tower::{Layer, Service};
+
+use crate::LruCache;
+
+type Shared<K> = Arc<Mutex<LruCache<K, CachedResponse>>>;
+type Cacheable = Arc<dyn Fn(&Parts) -> bool + Send + Sync>;
+
+// a response as the layer keeps it, with the body and trailers read into memory
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    parts: Parts,
+    body: Bytes,
+    trailers: Option<HeaderMap>
+}
+
+impl CachedResponse {
+    pub fn status(&self) -> StatusCode {
+        self.parts.status
+    }
+
+    // e.g. to weigh the cache by body size
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    fn response<B: Body>(&self) -> Response<CacheBody<B>> {
+        let data = Some(self.body.clone()).filter(|body| !body.is_empty());
+        let body = CacheBody(Inner::Cached { data, trailers: self.trailers.clone() });
+        Response::from_parts(self.parts.clone(), body)
+    }
+}
+
+// the layer's response body: a cached body, or the inner service's own when the response isn't cached
+pub struct CacheBody<B: Body>(Inner<B>);
+
+enum Inner<B: Body> {
+    Cached {
+        data: Option<Bytes>,
+        trailers: Option<HeaderMap>
+    },
+    Streaming(Pin<Box<B>>),
+    // reading the body to cache it failed, the error is handed on to the reader
+    Failed(Option<B::Error>)
+}
+
+// nothing is pinned in place, the inner body is pinned in its own box
+impl<B: Body> Unpin for CacheBody<B> {}
+
+impl<B: Body> Body for CacheBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        match &mut self.get_mut().0 {
+            Inner::Cached { data, trailers } => Poll::Ready(
+                data.take().map(Frame::data)
+                    .or_else(|| trailers.take().map(Frame::trailers))
+                    .map(Ok)
+            ),
+            Inner::Streaming(body) => body.as_mut().poll_frame(cx).map(|frame| {
+                frame.map(|frame| frame.map(|frame| frame.map_data(|mut data| data.copy_to_bytes(data.remaining()))))
+            }),
+            Inner::Failed(error) => Poll::Ready(error.take().map(Err))
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.0 {
+            Inner::Cached { data, trailers } => data.is_none() && trailers.is_none(),
+            Inner::Streaming(body) => body.is_end_stream(),
+            Inner::Failed(error) => error.is_none()
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.0 {
+            Inner::Cached { data, .. } => SizeHint::with_exact(data.as_ref().map_or(0, |data| data.len() as u64)),
+            Inner::Streaming(body) => body.size_hint(),
+            Inner::Failed(_) => SizeHint::default()
+        }
+    }
+}
+
+// a tower layer caching http responses under the key `key` extracts from the request. requests it returns None
+// for bypass the cache. by default only success statuses are cached, `cache_if` takes another predicate.
+// a cacheable response's body is read into memory before it is served, others stream through
+pub struct CacheLayer<K: Clone + Eq + Hash, F> {
+    cache: Shared<K>,
+    key: Arc<F>,
+    cacheable: Cacheable
+}
+
+impl<K: Clone + Eq + Hash, F> CacheLayer<K, F> {
+    pub fn new(cache: LruCache<K, CachedResponse>, key: F) -> Self {
+        CacheLayer {
+            cache: Arc::new(Mutex::new(cache)),
+            key: Arc::new(key),
+            cacheable: Arc::new(|parts: &Parts| parts.status.is_success())
+        }
+    }
+
+    pub fn cache_if<P: Fn(&Parts) -> bool + Send + Sync + 'static>(mut self, predicate: P) -> Self {
+        self.cacheable = Arc::new(predicate);
+        self
+    }
+}
+
+impl<K: Clone + Eq + Hash, F> Clone for CacheLayer<K, F> {
+    fn clone(&self) -> Self {
+        CacheLayer { cache: self.cache.clone(), key: self.key.clone(), cacheable: self.cacheable.clone() }
+    }
+}
+
+impl<S, K: Clone + Eq + Hash, F> Layer<S> for CacheLayer<K, F> {
+    type Service = CacheService<S, K, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheService { inner, cache: self.cache.clone(), key: self.key.clone(), cacheable: self.cacheable.clone() }
+    }
+}
+
+// clones share one cache, so every route instance sees the same entries
+pub struct CacheService<S, K: Clone + Eq + Hash, F> {
+    inner: S,
+    cache: Shared<K>,
+    key: Arc<F>,
+    cacheable: Cacheable
+}
+
+impl<S: Clone, K: Clone + Eq + Hash, F> Clone for CacheService<S, K, F> {
+    fn clone(&self) -> Self {
+        CacheService {
+            inner: self.inner.clone(),
+            cache: self.cache.clone(),
+            key: self.key.clone(),
+            cacheable: self.cacheable.clone()
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody, K, F> Service<Request<ReqBody>> for CacheService<S, K, F>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Body + Send + 'static,
+    ResBody::Data: Send,
+    ResBody::Error: Send,
+    K: Clone + Eq + Hash + Send + 'static,
+    F: Fn(&Request<ReqBody>) -> Option<K>
+{
+    type Response = Response<CacheBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let key = (self.key)(&request);
+        if let Some(key) = &key {
+            if let Some(cached) = self.cache.lock().unwrap().get(key) {
+                return Box::pin(async move { Ok(cached.response()) });
+            }
+        }
+
+        let response = self.inner.call(request);
+        let cache = self.cache.clone();
+        let cacheable = self.cacheable.clone();
+        Box::pin(async move {
+            let (parts, body) = response.await?.into_parts();
+            let Some(key) = key.filter(|_| cacheable(&parts)) else {
+                return Ok(Response::from_parts(parts, CacheBody(Inner::Streaming(Box::pin(body)))));
+            };
+            let collected = match body.collect().await {
+                Ok(collected) => collected,
+                Err(error) => return Ok(Response::from_parts(parts, CacheBody(Inner::Failed(Some(error)))))
+            };
+            let trailers = collected.trailers().cloned();
+            let cached = CachedResponse { parts, body: collected.to_bytes(), trailers };
+            let response = cached.response();
+            cache.lock().unwrap().put(key, cached);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::{Ready, ready};
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use http_body_util::Full;
+
+    use super::*;
+
+    // answers with the request's path as the body, and a 500 for /fail
+    #[derive(Clone, Default)]
+    struct Counter(Arc<Mutex<u32>>);
+
+    impl Service<Request<()>> for Counter {
+        type Response = Response<Full<Bytes>>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<Full<Bytes>>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            *self.0.lock().unwrap() += 1;
+            let path = request.uri().path().to_string();
+            let status = if path == "/fail" { StatusCode::INTERNAL_SERVER_ERROR } else { StatusCode::OK };
+            let response = Response::builder().status(status).body(Full::new(Bytes::from(path))).unwrap();
+            ready(Ok(response))
+        }
+    }
+
+    fn now<T>(future: impl Future<Output = T>) -> T {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("test services are always ready")
+        }
+    }
+
+    fn call<S>(service: &mut S, path: &str) -> (StatusCode, Bytes)
+    where
+        S: Service<Request<()>, Response = Response<CacheBody<Full<Bytes>>>, Error = Infallible>
+    {
+        let response = now(service.call(Request::get(path).body(()).unwrap())).unwrap();
+        let status = response.status();
+        (status, now(response.into_body().collect()).unwrap().to_bytes())
+    }
+
+    fn path(request: &Request<()>) -> Option<String> {
+        (request.uri().path() != "/private").then(|| request.uri().path().to_string())
+    }
+
+    #[test]
+    fn test_cache_layer() {
+        let counter = Counter::default();
+        let layer = CacheLayer::new(LruCache::new(10), path);
+        let mut service = layer.layer(counter.clone());
+
+        assert_eq!(call(&mut service, "/a"), (StatusCode::OK, Bytes::from("/a")));
+        assert_eq!(call(&mut service, "/a"), (StatusCode::OK, Bytes::from("/a")));
+        assert_eq!(*counter.0.lock().unwrap(), 1);
+
+        call(&mut service, "/private");
+        call(&mut service, "/private");
+        assert_eq!(*counter.0.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_cache_layer_skips_errors() {
+        let counter = Counter::default();
+        let mut service = CacheLayer::new(LruCache::new(10), path).layer(counter.clone());
+        assert_eq!(call(&mut service, "/fail").0, StatusCode::INTERNAL_SERVER_ERROR);
+        call(&mut service, "/fail");
+        assert_eq!(*counter.0.lock().unwrap(), 2);
+
+        let counter = Counter::default();
+        let layer = CacheLayer::new(LruCache::new(10), path).cache_if(|parts| parts.status.is_server_error());
+        let mut service = layer.layer(counter.clone());
+        call(&mut service, "/fail");
+        call(&mut service, "/fail");
+        call(&mut service, "/a");
+        call(&mut service, "/a");
+        assert_eq!(*counter.0.lock().unwrap(), 3);
+    }
+}
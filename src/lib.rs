@@ -1,23 +1,200 @@
+use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::fmt;
 use std::hash::Hash;
+use std::ops::{Add, Sub};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "audit")]
+use audit::AuditLog;
+use bloom::KeyFilter;
+use elastic::Growth;
+use expiry::ExpiryQueue;
+use index::ValueIndex;
+use slab::Slab;
+use stats::Windows;
+
+mod admission;
+mod any;
+mod arc;
+mod audit;
+mod bloom;
+#[cfg(feature = "async")]
+mod async_cache;
+mod bytes;
+mod cache;
+mod chain;
+mod clock_pro;
+mod concurrent;
+mod diff;
+mod elastic;
+mod error;
+mod expiry;
+mod frozen;
+mod fs;
+mod gdsf;
+mod group;
+mod heavy_hitters;
+#[cfg(feature = "http")]
+mod http_cache;
+mod index;
+mod info;
+mod invalidation;
+mod key;
+#[cfg(feature = "tower")]
+mod layer;
+mod lirs;
+mod loading;
+mod lru_k;
+mod multimap;
+mod options;
+mod outcome;
+mod partitioned;
+#[cfg(feature = "raw")]
+mod raw;
+#[cfg(test)]
+mod reference;
+mod report;
+pub mod replay;
+pub mod router;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "async")]
+pub mod runtime;
+#[cfg(feature = "redis")]
+mod redis_store;
+#[cfg(feature = "rand")]
+mod sample;
+mod set;
+#[cfg(feature = "sled")]
+mod sled_store;
+pub mod sim;
+mod sketch;
+mod slab;
+mod sorted;
+mod stats;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+#[cfg(feature = "mmap")]
+mod spill;
+mod store;
+mod wheel;
+pub mod workload;
+
+pub use admission::{AdmissionPolicy, AlwaysAdmit, HashKey, TinyLfu};
+pub use any::AnyLruCache;
+pub use arc::ArcCache;
+pub use audit::{AuditOp, AuditOutcome};
+#[cfg(feature = "audit")]
+pub use audit::AuditRecord;
+#[cfg(feature = "async")]
+pub use async_cache::{AsyncLoadingCache, EntriesStream};
+pub use bytes::BytesLruCache;
+pub use cache::Cache;
+pub use chain::Chain;
+pub use clock_pro::ClockProCache;
+pub use concurrent::{default_shard_count, ConcurrentLruCache, ReadHandle};
+pub use diff::CacheDiff;
+pub use error::{CacheError, CasError};
+pub use expiry::{Expiry, ExpiryBackend};
+pub use frozen::FrozenLruCache;
+pub use fs::FsCache;
+pub use gdsf::GdsfCache;
+pub use group::{CacheGroup, GroupedCache};
+pub use heavy_hitters::SpaceSaving;
+#[cfg(feature = "http")]
+pub use http_cache::HttpCache;
+pub use info::EntryInfo;
+pub use invalidation::{Coherent, InvalidationBus, MemoryBus, MemoryBusMember};
+pub use key::SmallKey;
+#[cfg(feature = "tower")]
+pub use layer::{CacheBody, CacheLayer, CacheService, CachedResponse};
+pub use lirs::LirsCache;
+pub use loading::{LoadingCache, Lookup};
+pub use lru_k::LruKCache;
+pub use multimap::LruMultiMap;
+pub use options::{CapacityPolicy, EntryOptions, MemoryPressure, OversizedPolicy};
+pub use outcome::InsertOutcome;
+pub use partitioned::PartitionedCache;
+#[cfg(feature = "raw")]
+pub use raw::RawLruCache;
+#[cfg(feature = "redis")]
+pub use redis_store::RedisStore;
+pub use report::DebugReport;
+#[cfg(feature = "sled")]
+pub use sled_store::SledStore;
+pub use set::LruSet;
+pub use sketch::FrequencySketch;
+pub use sorted::SortedLruCache;
+pub use stats::{CacheStats, WindowStats, WindowedStats};
+pub use store::{Store, StoreCache};
 
 struct LruNode<K, V> {
     key: K,
     value: V,
-    prev: Option<K>,
-    next: Option<K>
+    inserted_at: Instant,
+    // position in insertion order, kept when the value is overwritten
+    inserted_seq: u64,
+    // bumped on every write, unique across the cache
+    version: u64,
+    // caller supplied ordering from put_if_newer, None after a plain put
+    stamp: Option<u64>,
+    written_at: Instant,
+    accessed_at: Instant,
+    hits: u64,
+    weight: usize,
+    expires_at: Option<Instant>,
+    tti: Option<Duration>,
+    idle_at: Option<Instant>,
+    expiry_id: Option<u64>,
+    // slots of the neighbours in `nodes`
+    prev: Option<u32>,
+    next: Option<u32>
 }
 
 impl<K, V> LruNode<K, V> {
-    fn new(key: K, value: V) -> Self {
+    // a lifetime too long to be added to an Instant never expires
+    fn new(key: K, value: V, weight: usize, options: EntryOptions, now: Instant) -> Self {
         LruNode {
             key,
             value,
+            inserted_at: now,
+            inserted_seq: 0,
+            version: 0,
+            stamp: None,
+            written_at: now,
+            accessed_at: now,
+            hits: 0,
+            weight,
+            expires_at: options.ttl.and_then(|ttl| now.checked_add(ttl)),
+            tti: options.tti,
+            idle_at: options.tti.and_then(|tti| now.checked_add(tti)),
+            expiry_id: None,
             prev: None,
             next: None
         }
     }
+
+    fn deadline(&self) -> Option<Instant> {
+        match (self.expires_at, self.idle_at) {
+            (Some(expires_at), Some(idle_at)) => Some(expires_at.min(idle_at)),
+            (expires_at, idle_at) => expires_at.or(idle_at)
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.deadline().is_some_and(|deadline| deadline <= now)
+    }
+
+    fn touch(&mut self, now: Instant) {
+        self.accessed_at = now;
+        self.hits += 1;
+        if let Some(tti) = self.tti {
+            self.idle_at = now.checked_add(tti);
+        }
+    }
 }
 
 impl<K, V> Clone for LruNode<K, V>
@@ -29,185 +206,2230 @@ where
         LruNode {
             key: self.key.clone(),
             value: self.value.clone(),
-            prev: self.prev.clone(),
-            next: self.next.clone()
+            inserted_at: self.inserted_at,
+            inserted_seq: self.inserted_seq,
+            version: self.version,
+            stamp: self.stamp,
+            written_at: self.written_at,
+            accessed_at: self.accessed_at,
+            hits: self.hits,
+            weight: self.weight,
+            expires_at: self.expires_at,
+            tti: self.tti,
+            idle_at: self.idle_at,
+            expiry_id: self.expiry_id,
+            prev: self.prev,
+            next: self.next
         }
     }
 }
 
+// shared with caches split off this one
+type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> usize + Send + Sync>;
+type Admission<K> = Box<dyn AdmissionPolicy<K> + Send + Sync>;
+// slots in `nodes` are u32, so a cache never holds more than this many entries
+const MAX_SLOTS: usize = u32::MAX as usize;
+
+// the hasher behind the cache's own lookups. with the `ahash` feature it is ahash, several times faster than
+// std's SipHash on small keys but without its resistance to collision attacks from untrusted keys
+#[cfg(feature = "ahash")]
+pub(crate) type RandomState = ahash::RandomState;
+#[cfg(not(feature = "ahash"))]
+pub(crate) type RandomState = std::collections::hash_map::RandomState;
+
+// no method panics on its own account: the list is only followed through checked slot lookups that
+// tolerate links to missing slots, so even inconsistent links degrade to misses instead of aborting.
+// panics can still come from user code (weighers, predicates, policies, Hash/Eq impls)
 pub struct LruCache<K: Clone + Eq + Hash, V> {
     capacity: usize,
-    map: HashMap<K, RefCell<LruNode<K, V>>>,
-    head: Option<K>,
-    tail: Option<K>
+    defaults: EntryOptions,
+    weigher: Option<Weigher<K, V>>,
+    max_weight: usize,
+    max_entry_weight: usize,
+    weight: usize,
+    oversized: OversizedPolicy,
+    // fractions of the entries dropped at moderate and critical memory pressure
+    pressure_trim: (f64, f64),
+    promote_every: u64,
+    admission: Option<Admission<K>>,
+    filter: Option<KeyFilter>,
+    hot_keys: Option<SpaceSaving<K>>,
+    index: Option<ValueIndex<K, V>>,
+    stats: CacheStats,
+    windows: Option<Box<Windows>>,
+    growth: Option<Box<Growth>>,
+    next_seq: u64,
+    next_version: u64,
+    #[cfg(feature = "audit")]
+    audit: Option<AuditLog>,
+    expiries: ExpiryQueue<K>,
+    // key to slot in `nodes`, the list is linked through slots so following it needs no hashing
+    map: HashMap<K, u32, RandomState>,
+    nodes: Slab<LruNode<K, V>>,
+    head: Option<u32>,
+    tail: Option<u32>
 }
 
 impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
     pub fn new(capacity: usize) -> Self {
         LruCache {
             capacity,
-            map: HashMap::new(),
+            defaults: EntryOptions::default(),
+            weigher: None,
+            max_weight: usize::MAX,
+            max_entry_weight: usize::MAX,
+            weight: 0,
+            oversized: OversizedPolicy::default(),
+            pressure_trim: (0.25, 0.5),
+            promote_every: 1,
+            admission: None,
+            filter: None,
+            hot_keys: None,
+            index: None,
+            stats: CacheStats::default(),
+            windows: None,
+            growth: None,
+            next_seq: 0,
+            next_version: 0,
+            #[cfg(feature = "audit")]
+            audit: None,
+            expiries: ExpiryQueue::new(ExpiryBackend::Heap),
+            map: HashMap::default(),
+            nodes: Slab::new(),
             head: None,
             tail: None
         }
     }
 
-    pub fn get(&mut self, key: K) -> Option<V> {
-        let value = match self.map.get_mut(&key) {
-            None =>  return None,
-            Some(node_ref) => {
-                let node = node_ref.borrow();
-                node.value.clone()
+    pub fn try_new(capacity: usize) -> Result<Self, CacheError> {
+        if capacity == 0 {
+            return Err(CacheError::ZeroCapacity);
+        }
+        Ok(LruCache::new(capacity))
+    }
+
+    // every entry put without an explicit ttl expires after `ttl`
+    pub fn with_ttl(capacity: usize, ttl: Duration) -> Self {
+        LruCache::with_options(capacity, EntryOptions::ttl(ttl))
+    }
+
+    pub fn with_options(capacity: usize, defaults: EntryOptions) -> Self {
+        let mut cache = LruCache::new(capacity);
+        cache.defaults = defaults;
+        cache
+    }
+
+    // bounds the cache by the total weight of its entries as well as their count,
+    // without a weigher every entry weighs 1
+    pub fn weigh_by<F>(mut self, max_weight: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + Sync + 'static
+    {
+        self.weigher = Some(Arc::new(weigher));
+        self.max_weight = max_weight;
+        self.trim();
+        self
+    }
+
+    // values heavier than this are never cached however much room is left, so one key can't monopolize
+    // the budget. they are handled by the oversized policy
+    pub fn max_entry_weight(mut self, max_entry_weight: usize) -> Self {
+        self.max_entry_weight = max_entry_weight;
+        self
+    }
+
+    // how values heavier than max_weight or max_entry_weight are handled, by default they are evicted on arrival
+    // instead of flushing the rest of the cache
+    pub fn oversized(mut self, policy: OversizedPolicy) -> Self {
+        self.oversized = policy;
+        self
+    }
+
+    // with CapacityPolicy::Elastic the capacity grows while misses are mostly on keys evicted for room
+    pub fn capacity_policy(mut self, policy: CapacityPolicy) -> Self {
+        self.growth = Growth::for_policy(policy);
+        self
+    }
+
+    // how much on_pressure drops at each level, as fractions of the entries and weight. 0.25 and 0.5 by default
+    pub fn trim_on_pressure(mut self, moderate: f64, critical: f64) -> Self {
+        self.pressure_trim = (moderate.clamp(0.0, 1.0), critical.clamp(0.0, 1.0));
+        self
+    }
+
+    // moves an entry to the most recently used end only on every `n`th read of it instead of every read.
+    // for very read hot keys this saves most of the list updates at little cost in hit rate
+    pub fn promote_every(mut self, n: u64) -> Self {
+        self.promote_every = n.max(1);
+        self
+    }
+
+    // consulted before a new key evicts anything, see AdmissionPolicy
+    pub fn admission<P>(mut self, policy: P) -> Self
+    where
+        P: AdmissionPolicy<K> + Send + Sync + 'static
+    {
+        self.admission = Some(Box::new(policy));
+        self
+    }
+
+    // keeps a bloom filter over the present keys so most misses are answered without probing the map,
+    // for caches whose misses are common and expensive to look up
+    pub fn bloom_filter(mut self, false_positive_rate: f64) -> Self {
+        let mut filter = KeyFilter::new(self.capacity.min(1 << 20), false_positive_rate);
+        filter.rebuild(self.map.keys());
+        self.filter = Some(filter);
+        self
+    }
+
+    // counts lookups, hits and misses alike, in a space-saving sketch of `slots` counters
+    // so heavy_hitters can report the most requested keys
+    pub fn track_heavy_hitters(mut self, slots: usize) -> Self {
+        self.hot_keys = Some(SpaceSaving::new(slots));
+        self
+    }
+
+    // maintains a reverse index from `index(value)` to the keys holding such values,
+    // so invalidate_by_index can drop them all without a scan
+    pub fn index_by<I, F>(mut self, index: F) -> Self
+    where
+        I: Hash,
+        F: Fn(&V) -> I + Send + Sync + 'static
+    {
+        let mut value_index = ValueIndex::new(index);
+        for node in self.nodes.iter() {
+            value_index.insert(&node.key, &node.value);
+        }
+        self.index = Some(value_index);
+        self
+    }
+
+    // counts hits and misses in 10 second buckets over the last 15 minutes for windowed_stats
+    pub fn track_windowed_stats(mut self) -> Self {
+        self.windows = Some(Box::new(Windows::new()));
+        self
+    }
+
+    // keeps the last `capacity` operations with their outcome for recent_ops
+    #[cfg(feature = "audit")]
+    pub fn audit_log(mut self, capacity: usize) -> Self {
+        self.audit = Some(AuditLog::new(capacity));
+        self
+    }
+
+    // recent operations oldest first, to reconstruct why a key disappeared.
+    // empty unless audit_log was set
+    #[cfg(feature = "audit")]
+    pub fn recent_ops(&self) -> Vec<AuditRecord> {
+        self.audit.as_ref().map_or_else(Vec::new, AuditLog::records)
+    }
+
+    // every operation passes through here, feeding the stats counters and the audit log if there is one
+    fn audit(&mut self, op: AuditOp, key: Option<&dyn HashKey>, outcome: AuditOutcome) {
+        self.stats.record(op, outcome);
+        if let Some(windows) = &mut self.windows {
+            windows.record(op, outcome);
+        }
+        if let (Some(growth), Some(key)) = (&mut self.growth, key) {
+            match (op, outcome) {
+                (AuditOp::Evict, AuditOutcome::Removed) => growth.evicted(key),
+                (AuditOp::Get, AuditOutcome::Miss) => if let Some(capacity) = growth.missed(key, self.capacity) {
+                    self.capacity = capacity;
+                },
+                _ => ()
+            }
+        }
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &mut self.audit {
+            audit.record(op, key, outcome);
+        }
+        #[cfg(not(feature = "audit"))]
+        let _ = (op, key, outcome);
+    }
+
+    // switches how deadlines are tracked for purge_expired, the timer wheel suits
+    // caches with very many short lived entries
+    pub fn expiry_backend(mut self, backend: ExpiryBackend) -> Self {
+        self.expiries = ExpiryQueue::new(backend);
+        for node in self.nodes.iter_mut() {
+            node.expiry_id = node.deadline().map(|deadline| self.expiries.schedule(node.key.clone(), deadline));
+        }
+        self
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.get_with_expiry(key).map(|(value, _)| value)
+    }
+
+    // also returns the remaining lifetime, None for entries that never expire
+    pub fn get_with_expiry<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<(V, Option<Duration>)>
+    where
+        K: Borrow<Q>
+    {
+        let now = Instant::now();
+        let (value, deadline) = self.read(key, now)?;
+        Some((value, deadline.map(|deadline| deadline.saturating_duration_since(now))))
+    }
+
+    // the bookkeeping of a hit whose value was read earlier through a shared borrow, e.g. under a read lock:
+    // counts it, refreshes the entry's idle deadline and promotes it when due. the entry may be gone by now
+    pub(crate) fn record_hit<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        if let Some(admission) = &mut self.admission {
+            admission.record(&key);
+        }
+        if let Some(slot) = self.map.get(key).copied() {
+            let node = &mut self.nodes[slot];
+            if let Some(hot_keys) = &mut self.hot_keys {
+                hot_keys.record::<K>(&node.key);
             }
+            node.touch(Instant::now());
+            if node.hits.is_multiple_of(self.promote_every) {
+                self.relink_back(slot);
+            }
+        }
+        self.audit(AuditOp::Get, Some(&key), AuditOutcome::Hit);
+    }
+
+    // everything a get does, returning the value and its deadline
+    fn read<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q, now: Instant) -> Option<(V, Option<Instant>)>
+    where
+        K: Borrow<Q>
+    {
+        let slot = self.lookup(key, now)?;
+        let node = &self.nodes[slot];
+        Some((node.value.clone(), node.deadline()))
+    }
+
+    // a lookup's bookkeeping, hit or miss, returning the live entry's slot on a hit
+    fn lookup<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q, now: Instant) -> Option<u32>
+    where
+        K: Borrow<Q>
+    {
+        // a borrowed form may be unsized, its reference is what the policy and audit log hash
+        if let Some(admission) = &mut self.admission {
+            admission.record(&key);
+        }
+        if let Some(hot_keys) = &mut self.hot_keys {
+            hot_keys.record(key);
+        }
+        if !self.might_contain(key) {
+            self.audit(AuditOp::Get, Some(&key), AuditOutcome::Miss);
+            return None;
+        }
+        let Some(slot) = self.map.get(key).copied() else {
+            self.audit(AuditOp::Get, Some(&key), AuditOutcome::Miss);
+            return None;
         };
-    
-        self.move_to_back(&key);
-        Some(value)
+        let node = &mut self.nodes[slot];
+        if node.is_expired(now) {
+            self.evict_node(key, AuditOp::Expire);
+            self.audit(AuditOp::Get, Some(&key), AuditOutcome::Miss);
+            return None;
+        }
+        node.touch(now);
+        if node.hits.is_multiple_of(self.promote_every) {
+            self.relink_back(slot);
+        }
+        self.audit(AuditOp::Get, Some(&key), AuditOutcome::Hit);
+        Some(slot)
+    }
+
+    // also returns the entry's version, which changes on every write to it (even one storing an equal value)
+    // and is never reused within the cache, so a later get_versioned tells whether it changed in between
+    pub fn get_versioned<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<(V, u64)>
+    where
+        K: Borrow<Q>
+    {
+        let value = self.get(key)?;
+        let version = self.node(key)?.version;
+        Some((value, version))
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.might_contain(key) && self.node(key).is_some_and(|node| !node.is_expired(Instant::now()))
+    }
+
+    fn might_contain<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.filter.as_ref().is_none_or(|filter| filter.might_contain(key))
+    }
+
+    // reads a value without changing its recency
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.live_node(key).map(|node| node.value.clone())
+    }
+
+    // like peek, also handing back the key as stored
+    pub(crate) fn peek_entry<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>
+    {
+        self.live_node(key).map(|node| (node.key.clone(), node.value.clone()))
     }
 
-    fn move_to_back(&mut self, key: &K) {
-        if let Some(node_ref) = self.map.get(&key).cloned() {
-            self.remove_node(&node_ref);
-            self.append_node(&node_ref);
+    // the entry for `key` unless it has expired, consulting the bloom filter first
+    fn live_node<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&LruNode<K, V>>
+    where
+        K: Borrow<Q>
+    {
+        if !self.might_contain(key) {
+            return None;
         }
+        self.node(key).filter(|node| !node.is_expired(Instant::now()))
     }
 
+    // like get, but an entry expired less than `grace` ago is returned flagged as stale instead of dropped
+    pub(crate) fn get_stale<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q, grace: Duration) -> Option<(V, bool)>
+    where
+        K: Borrow<Q>
+    {
+        let now = Instant::now();
+        let stale = match self.node(key)?.deadline() {
+            Some(deadline) if deadline <= now => Some(deadline.checked_add(grace).is_none_or(|end| end > now)),
+            _ => None
+        };
 
-    pub fn put(&mut self, key: K, value: V) {
-        if let Some(node_ref) = self.map.get(&key).cloned() {
-            let mut node = node_ref.borrow_mut();
-            node.value = value;
-            drop(node); // Explicitly drop the mutable borrow
-            self.remove_node(&node_ref);
-            self.append_node(&node_ref);
-        } else {
-            if self.map.len() == self.capacity {
-                if let Some(head_ref) = self.head.as_ref().cloned() {
-                    self.evict_node(&head_ref)
-                }
+        match stale {
+            None => self.get(key).map(|value| (value, false)),
+            Some(true) => self.peek_expired(key).map(|value| (value, true)),
+            Some(false) => {
+                self.evict_node(key, AuditOp::Expire);
+                None
+            }
+        }
+    }
+
+    fn peek_expired<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.node(key).map(|node| node.value.clone())
+    }
+
+    pub fn entry_info<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<EntryInfo>
+    where
+        K: Borrow<Q>
+    {
+        let node = self.node(key)?;
+        Some(EntryInfo {
+            inserted_at: node.inserted_at,
+            last_accessed: node.accessed_at,
+            access_count: node.hits,
+            weight: node.weight,
+            expires_at: node.deadline()
+        })
+    }
+
+    // live entries, least recently used first
+    pub fn entries(&self) -> Vec<(K, V)> {
+        let now = Instant::now();
+        self.slots_in_order().into_iter()
+            .map(|slot| &self.nodes[slot])
+            .filter(|node| !node.is_expired(now))
+            .map(|node| (node.key.clone(), node.value.clone()))
+            .collect()
+    }
+
+    // the n most recently used live entries, most recent first. doesn't touch recency
+    pub fn most_recent(&self, n: usize) -> Vec<(K, V)> {
+        self.walk(self.tail, n, |node| node.prev)
+    }
+
+    // the n least recently used live entries, least recent first. doesn't touch recency
+    pub fn least_recent(&self, n: usize) -> Vec<(K, V)> {
+        self.walk(self.head, n, |node| node.next)
+    }
+
+    // up to n live entries following links from start, stopping after len steps like slots_in_order
+    fn walk(&self, start: Option<u32>, n: usize, step: fn(&LruNode<K, V>) -> Option<u32>) -> Vec<(K, V)> {
+        let now = Instant::now();
+        let mut entries = Vec::with_capacity(n.min(self.nodes.len()));
+        let mut current = start;
+        let mut steps = 0;
+        while let Some(slot) = current {
+            if entries.len() == n || steps == self.nodes.len() {
+                break;
+            }
+            let Some(node) = self.nodes.get(slot) else { break };
+            if !node.is_expired(now) {
+                entries.push((node.key.clone(), node.value.clone()));
+            }
+            current = step(node);
+            steps += 1;
+        }
+        entries
+    }
+
+    // live entries in the order they were first put, oldest first. overwriting a value keeps its place,
+    // unlike entries which follows use
+    pub fn iter_by_insertion(&self) -> impl Iterator<Item = (K, V)> {
+        let now = Instant::now();
+        let mut entries: Vec<(u64, K, V)> = self.nodes.iter()
+            .filter(|node| !node.is_expired(now))
+            .map(|node| (node.inserted_seq, node.key.clone(), node.value.clone()))
+            .collect();
+        entries.sort_unstable_by_key(|(seq, _, _)| *seq);
+        entries.into_iter().map(|(_, key, value)| (key, value))
+    }
+
+    // most accessed entries first, ties broken by recency
+    pub fn top_n_by_hits(&self, n: usize) -> Vec<(K, u64)> {
+        let mut hits = self.hits_in_order();
+        hits.reverse();
+        hits.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+        hits.truncate(n);
+        hits
+    }
+
+    // least accessed entries first, ties broken by recency
+    pub fn coldest_n(&self, n: usize) -> Vec<(K, u64)> {
+        let mut hits = self.hits_in_order();
+        hits.sort_by_key(|(_, hits)| *hits);
+        hits.truncate(n);
+        hits
+    }
+
+    // the most requested keys with estimated request counts, including keys that missed.
+    // empty unless track_heavy_hitters was set
+    pub fn heavy_hitters(&self, n: usize) -> Vec<(K, u64)> {
+        self.hot_keys.as_ref().map_or_else(Vec::new, |hot_keys| hot_keys.top(n))
+    }
+
+    fn hits_in_order(&self) -> Vec<(K, u64)> {
+        self.slots_in_order().into_iter()
+            .map(|slot| &self.nodes[slot])
+            .map(|node| (node.key.clone(), node.hits))
+            .collect()
+    }
+
+    fn keys_in_order(&self) -> Vec<K> {
+        self.slots_in_order().into_iter().map(|slot| self.nodes[slot].key.clone()).collect()
+    }
+
+    // least recently used first, every slot returned is in bounds. stops at a dangling link,
+    // and after len steps in case links loop
+    fn slots_in_order(&self) -> Vec<u32> {
+        let mut slots = Vec::with_capacity(self.nodes.len());
+        let mut current = self.head;
+        while let Some(slot) = current {
+            if slots.len() == self.nodes.len() {
+                break;
+            }
+            let Some(node) = self.nodes.get(slot) else { break };
+            current = node.next;
+            slots.push(slot);
+        }
+        slots
+    }
+
+    fn node<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<&LruNode<K, V>>
+    where
+        K: Borrow<Q>
+    {
+        self.nodes.get(*self.map.get(key)?)
+    }
+
+    fn node_mut<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<&mut LruNode<K, V>>
+    where
+        K: Borrow<Q>
+    {
+        self.nodes.get_mut(*self.map.get(key)?)
+    }
+
+    // when the least recently used entry was last read or written
+    pub(crate) fn oldest_use(&self) -> Option<Instant> {
+        let node = self.nodes.get(self.head?)?;
+        Some(node.accessed_at.max(node.written_at))
+    }
+
+    pub(crate) fn time_to_live<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<Duration>
+    where
+        K: Borrow<Q>
+    {
+        let deadline = self.node(key)?.deadline()?;
+        Some(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    // how far through its time to live an entry is, from 0.0 when written to 1.0 at expiry
+    pub(crate) fn ttl_progress<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<f64>
+    where
+        K: Borrow<Q>
+    {
+        let node = self.node(key)?;
+        let total = node.expires_at?.duration_since(node.written_at);
+        if total.is_zero() {
+            return Some(1.0);
+        }
+        Some(node.written_at.elapsed().as_secs_f64() / total.as_secs_f64())
+    }
+
+
+    pub fn put(&mut self, key: K, value: V) -> InsertOutcome {
+        self.insert(key, value, self.defaults).0
+    }
+
+    // returns the entries evicted to make room, or the new entry itself if the admission policy rejected it
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.insert(key, value, self.defaults).1
+    }
+
+    pub fn try_put(&mut self, key: K, value: V) -> Result<(), CacheError> {
+        self.try_push(key, value).map(drop)
+    }
+
+    // like push, but a value heavier than the whole budget or one the admission policy
+    // turns away is an error instead of being silently dropped
+    pub fn try_push(&mut self, key: K, value: V) -> Result<Vec<(K, V)>, CacheError> {
+        let weight = self.weigh(&key, &value);
+        if weight > self.entry_limit() {
+            return Err(CacheError::ValueTooHeavy { weight, max_weight: self.entry_limit() });
+        }
+        let evicted = self.push(key.clone(), value);
+        if !self.map.contains_key(&key) {
+            return Err(CacheError::Rejected);
+        }
+        Ok(evicted)
+    }
+
+    // inserts only if there is no live entry, otherwise returns the existing value without touching its recency
+    pub fn put_if_absent(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(existing) = self.peek(&key) {
+            return Some(existing);
+        }
+        self.put(key, value);
+        None
+    }
+
+    // mutates the cached value in place and promotes it, without cloning it out and back in. the entry is reweighed
+    // afterwards and keeps its expiry. false if there was no live entry
+    pub fn update<Q: ?Sized + Hash + Eq, F: FnOnce(&mut V)>(&mut self, key: &Q, update: F) -> bool
+    where
+        K: Borrow<Q>
+    {
+        let now = Instant::now();
+        let Some(slot) = self.map.get(key).copied() else { return false };
+        let node = &mut self.nodes[slot];
+        if node.is_expired(now) {
+            self.evict_node(key, AuditOp::Expire);
+            return false;
+        }
+        if let Some(index) = &mut self.index {
+            index.remove(&node.key, &node.value);
+        }
+        update(&mut node.value);
+        if let Some(index) = &mut self.index {
+            index.insert(&node.key, &node.value);
+        }
+        self.next_version += 1;
+        node.version = self.next_version;
+        node.touch(now);
+        let weight = self.weigher.as_ref().map_or(1, |weigher| weigher(&node.key, &node.value));
+        self.weight = self.weight.saturating_sub(node.weight).saturating_add(weight);
+        node.weight = weight;
+        self.relink_back(slot);
+
+        self.audit(AuditOp::Put, Some(&key), AuditOutcome::Updated);
+        if weight > self.entry_limit() {
+            self.evict_node(key, AuditOp::Evict);
+        }
+        self.trim();
+        true
+    }
+
+    // promotes a live entry and restarts its time to live at `ttl` from now, without rewriting the value, so
+    // sessions can be extended on activity. an idle timeout stays as it was. false if there was no live entry
+    pub fn touch_with_ttl<Q: ?Sized + Hash + Eq>(&mut self, key: &Q, ttl: Duration) -> bool
+    where
+        K: Borrow<Q>
+    {
+        let now = Instant::now();
+        let Some(slot) = self.map.get(key).copied() else { return false };
+        let node = &mut self.nodes[slot];
+        if node.is_expired(now) {
+            self.evict_node(key, AuditOp::Expire);
+            return false;
+        }
+        node.touch(now);
+        node.expires_at = now.checked_add(ttl);
+        if let Some(id) = node.expiry_id.take() {
+            self.expiries.cancel(id);
+        }
+        if let Some(deadline) = node.deadline() {
+            node.expiry_id = Some(self.expiries.schedule(node.key.clone(), deadline));
+        }
+        self.relink_back(slot);
+        true
+    }
+
+    // reads the cached value like get, inserting V::default() on a miss, and lends it out to accumulate into. the
+    // borrow outlives the call, so unlike update the entry isn't reweighed or reindexed after the caller's change.
+    // the default is asked for rather than offered, so the admission policy is skipped. fails with ValueTooHeavy if
+    // the default alone weighs more than an entry may
+    pub fn get_or_default(&mut self, key: K) -> Result<&mut V, CacheError>
+    where
+        V: Default
+    {
+        let now = Instant::now();
+        if let Some(slot) = self.lookup(&key, now) {
+            return Ok(&mut self.nodes[slot].value);
+        }
+        let value = V::default();
+        let weight = self.weigh(&key, &value);
+        if weight > self.entry_limit() {
+            self.audit(AuditOp::Put, Some(&key), AuditOutcome::Rejected);
+            return Err(CacheError::ValueTooHeavy { weight, max_weight: self.entry_limit() });
+        }
+        self.store(key.clone(), value, weight, self.defaults, now, false);
+        // a stored entry is the most recently used
+        let slot = self.tail.filter(|&slot| self.nodes.get(slot).is_some_and(|node| node.key == key));
+        Ok(&mut self.nodes[slot.ok_or(CacheError::Rejected)?].value)
+    }
+
+    // inserts `value`, or if the key is cached replaces the value with `merge(cached, value)` in place,
+    // for aggregation caches that fold incoming values into what they hold
+    pub fn upsert<F: FnOnce(&V, V) -> V>(&mut self, key: K, value: V, merge: F) {
+        let mut value = Some(value);
+        let merged = self.update(&key, |cached| {
+            if let Some(value) = value.take() {
+                *cached = merge(cached, value);
+            }
+        });
+        if let (false, Some(value)) = (merged, value) {
+            self.put(key, value);
+        }
+    }
+
+    // extends a cached collection with `items` in place, starting from V::default() if there is none. like update,
+    // an existing collection is reweighed and keeps its expiry
+    pub fn append<T, I: IntoIterator<Item = T>>(&mut self, key: K, items: I)
+    where
+        V: Extend<T> + Default
+    {
+        let mut items = Some(items);
+        let extended = self.update(&key, |values| values.extend(items.take().into_iter().flatten()));
+        if let (false, Some(items)) = (extended, items) {
+            let mut values = V::default();
+            values.extend(items);
+            self.put(key, values);
+        }
+    }
+
+    // adds `delta` to a cached counter, starting from V::default() if there is none, and returns the new count.
+    // like update, an existing counter keeps its expiry, so a ttl bounds a rate limiter's window
+    pub fn increment(&mut self, key: K, delta: V) -> V
+    where
+        V: Add<Output = V> + Default
+    {
+        self.apply_counter(key, |count| count + delta.clone())
+    }
+
+    pub fn decrement(&mut self, key: K, delta: V) -> V
+    where
+        V: Sub<Output = V> + Default
+    {
+        self.apply_counter(key, |count| count - delta.clone())
+    }
+
+    fn apply_counter<F: Fn(V) -> V>(&mut self, key: K, apply: F) -> V
+    where
+        V: Default
+    {
+        let mut updated = None;
+        self.update(&key, |count| {
+            *count = apply(count.clone());
+            updated = Some(count.clone());
+        });
+        updated.unwrap_or_else(|| {
+            let count = apply(V::default());
+            self.put(key, count.clone());
+            count
+        })
+    }
+
+    // stores the value only if `stamp` (a timestamp or sequence number) is strictly newer than the stamp of the
+    // cached entry, so events applied out of order can't overwrite what newer ones wrote. an entry from a plain put
+    // has no stamp and is always replaced. returns whether the value was stored
+    pub fn put_if_newer(&mut self, key: K, value: V, stamp: u64) -> bool {
+        let current = self.node(&key)
+            .filter(|node| !node.is_expired(Instant::now()))
+            .and_then(|node| node.stamp);
+        if current.is_some_and(|current| current >= stamp) {
+            return false;
+        }
+        if self.put(key.clone(), value) == InsertOutcome::Rejected {
+            return false;
+        }
+        match self.node_mut(&key) {
+            None => false,
+            Some(node) => {
+                node.stamp = Some(stamp);
+                true
             }
-    
-            let node = LruNode::new(key.clone(), value);
-            let node_ref = RefCell::new(node);
-            self.map.insert(key.clone(), node_ref.clone());
-            self.append_node(&node_ref);
         }
+    }
+
+    // replaces the value only if it still equals `expected`, for optimistic updates without a lock around get and put
+    pub fn compare_and_swap<Q: ?Sized + Hash + Eq>(&mut self, key: &Q, expected: &V, new: V) -> Result<(), CasError<V>>
+    where
+        K: Borrow<Q>,
+        V: PartialEq
+    {
+        let Some(node) = self.live_node(key) else { return Err(CasError::Missing) };
+        if node.value != *expected {
+            return Err(CasError::Mismatch(node.value.clone()));
+        }
+        match self.put(node.key.clone(), new) {
+            InsertOutcome::Rejected => Err(CasError::Rejected),
+            _ => Ok(())
+        }
+    }
+
+    // `update` sees the current value and returns its replacement, or None to leave it. false if nothing was replaced
+    pub fn update_if<Q: ?Sized + Hash + Eq, F: FnOnce(&V) -> Option<V>>(&mut self, key: &Q, update: F) -> bool
+    where
+        K: Borrow<Q>
+    {
+        let Some(node) = self.live_node(key) else { return false };
+        match update(&node.value) {
+            None => false,
+            Some(new) => self.put(node.key.clone(), new) != InsertOutcome::Rejected
+        }
+    }
+
+    // removes the entry only if `predicate` holds for its current value, so a read-modify-delete doesn't drop
+    // a value written in between. returns the removed value
+    pub fn remove_if<Q: ?Sized + Hash + Eq, F: FnOnce(&V) -> bool>(&mut self, key: &Q, predicate: F) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        if !self.peek(key).as_ref().is_some_and(predicate) {
+            return None;
+        }
+        self.remove(key)
+    }
+
+    pub fn put_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> InsertOutcome {
+        self.put_with_options(key, value, EntryOptions::ttl(ttl))
+    }
+
+    pub fn put_with_options(&mut self, key: K, value: V, options: EntryOptions) -> InsertOutcome {
+        self.insert(key, value, options.or(self.defaults)).0
+    }
+
+    pub fn put_expiring(&mut self, key: K, value: V) -> InsertOutcome
+    where
+        V: Expiry
+    {
+        let ttl = value.expires_at().map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        self.insert(key, value, EntryOptions { ttl, tti: None }.or(self.defaults)).0
+    }
 
+    // like push, but the value's own deadline overrides the cache ttl
+    pub fn push_expiring(&mut self, key: K, value: V) -> Vec<(K, V)>
+    where
+        V: Expiry
+    {
+        let ttl = value.expires_at().map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        self.insert(key, value, EntryOptions { ttl, tti: None }.or(self.defaults)).1
     }
 
-    fn evict_node (&mut self, key: &K) {
-        let node = self.map.get(key).unwrap().clone();
-        self.remove_node(&node);
-        self.map.remove(key);
+    // returns what became of the entry along with the entries evicted
+    fn insert(&mut self, key: K, value: V, options: EntryOptions) -> (InsertOutcome, Vec<(K, V)>) {
+        let weight = self.weigh(&key, &value);
+        if weight > self.entry_limit() {
+            return (InsertOutcome::Rejected, self.insert_oversized(key, value));
+        }
+        if let Some(admission) = &mut self.admission {
+            admission.record(&key);
+        }
+        self.store(key, value, weight, options, Instant::now(), true)
     }
 
-    fn remove_node(&mut self, node: &RefCell<LruNode<K, V>>) {
-        let (prev_ref, next_ref) = {
-            let node_borrow = node.borrow();
-            (node_borrow.prev.clone(), node_borrow.next.clone())
+    // insert past the weight check, and past admission too unless `admit` is set. the key is hashed once, for the
+    // map entry that tells an update from an insert
+    fn store(&mut self, key: K, value: V, weight: usize, options: EntryOptions, now: Instant, admit: bool) -> (InsertOutcome, Vec<(K, V)>) {
+        let full = self.map.len() >= self.capacity || self.weight.saturating_add(weight) > self.max_weight;
+        let slot = match self.map.entry(key) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let victim = self.head.and_then(|slot| self.nodes.get(slot));
+                if let (Some(victim), Some(admission)) = (victim.filter(|_| admit && full), &mut self.admission) {
+                    if !admission.admit(entry.key(), &victim.key) {
+                        let key = entry.into_key();
+                        self.audit(AuditOp::Put, Some(&key), AuditOutcome::Rejected);
+                        return (InsertOutcome::Rejected, vec![(key, value)]);
+                    }
+                }
+
+                let key = entry.key().clone();
+                let mut node = LruNode::new(key.clone(), value, weight, options, now);
+                self.next_version += 1;
+                node.version = self.next_version;
+                node.expiry_id = node.deadline().map(|deadline| self.expiries.schedule(key.clone(), deadline));
+                node.inserted_seq = self.next_seq;
+                self.next_seq += 1;
+                if let Some(filter) = &mut self.filter {
+                    filter.insert(&key);
+                }
+                if let Some(index) = &mut self.index {
+                    index.insert(&key, &node.value);
+                }
+                let slot = self.nodes.insert(node);
+                entry.insert(slot);
+                self.link_back(slot);
+                self.weight = self.weight.saturating_add(weight);
+
+                // the new entry is the most recently used, so only a capacity of 0 evicts it right away
+                let mut evicted = Vec::new();
+                if self.map.len() > self.capacity.min(MAX_SLOTS) {
+                    evicted.extend(self.pop_lru());
+                }
+                self.audit(AuditOp::Put, Some(&key), AuditOutcome::Inserted);
+                evicted.extend(self.trim());
+                return (InsertOutcome::Inserted, evicted);
+            }
         };
 
-        match prev_ref.clone() {
-            None => {
-                self.head = next_ref.clone();
-            },
-            Some(prev_ref) => {
-                self.map.get(&prev_ref).unwrap().borrow_mut().next = next_ref.clone();
+        let key = self.nodes[slot].key.clone();
+        // an expired entry is already gone as far as callers can tell, so writing its key inserts afresh
+        if self.nodes[slot].is_expired(now) {
+            self.evict_node(&key, AuditOp::Expire);
+            return self.store(key, value, weight, options, now, admit);
+        }
+        let mut node = LruNode::new(key.clone(), value, weight, options, now);
+        self.next_version += 1;
+        node.version = self.next_version;
+        node.expiry_id = node.deadline().map(|deadline| self.expiries.schedule(key.clone(), deadline));
+        {
+            let existing = &mut self.nodes[slot];
+            self.weight = self.weight.saturating_sub(existing.weight).saturating_add(weight);
+            if let Some(id) = existing.expiry_id {
+                self.expiries.cancel(id);
+            }
+            if let Some(index) = &mut self.index {
+                index.remove(&key, &existing.value);
+                index.insert(&key, &node.value);
             }
+            existing.value = node.value;
+            existing.version = node.version;
+            existing.stamp = node.stamp;
+            existing.weight = weight;
+            existing.written_at = node.written_at;
+            existing.expires_at = node.expires_at;
+            existing.tti = node.tti;
+            existing.idle_at = node.idle_at;
+            existing.expiry_id = node.expiry_id;
         }
-    
-        match next_ref {
-            None => {
-                self.tail = prev_ref.clone();
-            },
-            Some(next_ref) => {
-                self.map.get(&next_ref).unwrap().borrow_mut().prev = prev_ref;
+        self.relink_back(slot);
+        self.audit(AuditOp::Put, Some(&key), AuditOutcome::Updated);
+        (InsertOutcome::Updated, self.trim())
+    }
+
+    // whether all of `entries` fit at once without evicting one another, duplicate keys are counted twice
+    pub(crate) fn check_batch<'a>(&self, entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> Result<(), CacheError>
+    where
+        K: 'a,
+        V: 'a
+    {
+        let (mut count, mut total) = (0usize, 0usize);
+        for (key, value) in entries {
+            let weight = self.weigh(key, value);
+            if weight > self.entry_limit() {
+                return Err(CacheError::ValueTooHeavy { weight, max_weight: self.entry_limit() });
             }
+            count += 1;
+            total = total.saturating_add(weight);
         }
+        if count > self.capacity || total > self.max_weight {
+            return Err(CacheError::BatchTooLarge);
+        }
+        Ok(())
     }
 
-    fn append_node(&mut self, node: &RefCell<LruNode<K, V>>) {
-        match self.tail {
-            None => {
-                let key = node.borrow().key.clone();
-                self.head = Some(key.clone());
-                self.tail = Some(key.clone());
+    fn entry_limit(&self) -> usize {
+        self.max_entry_weight.min(self.max_weight)
+    }
+
+    // every policy counts the put as rejected, the value isn't cached whichever way it is handed back
+    fn insert_oversized(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let evicted = match self.oversized {
+            OversizedPolicy::Evict => {
+                self.evict_node(&key, AuditOp::Evict);
+                vec![(key.clone(), value)]
             },
-            Some(_) => {
-                let mut node = node.borrow_mut();
-                node.prev = self.tail.clone();
+            OversizedPolicy::Reject => vec![(key.clone(), value)],
+            OversizedPolicy::Bypass => {
+                self.remove(&key);
+                Vec::new()
+            }
+        };
+        self.audit(AuditOp::Put, Some(&key), AuditOutcome::Rejected);
+        evicted
+    }
+
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        self.weigher.as_ref().map_or(1, |weigher| weigher(key, value))
+    }
 
-                let mut tail_node = self.map.get(self.tail.as_ref().unwrap()).unwrap().borrow_mut();
-                tail_node.next = Some(node.key.clone());
+    fn trim(&mut self) -> Vec<(K, V)> {
+        self.trim_to_weight(self.max_weight)
+    }
 
-                self.tail = Some(node.key.clone());
+    // evicts least recently used entries until at most `len` are left
+    pub fn trim_to_len(&mut self, len: usize) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.map.len() > len {
+            match self.pop_lru() {
+                None => break,
+                Some(entry) => evicted.push(entry)
             }
         }
+        evicted
     }
 
-    pub fn delete(&mut self, key: K) {
-        if let Some(node_ref) = self.map.get(&key).cloned() {
-            self.remove_node(&node_ref);
-            self.map.remove(&key);
+    // evicts least recently used entries until the total weight is at most `weight`
+    pub fn trim_to_weight(&mut self, weight: usize) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.weight > weight {
+            match self.pop_lru() {
+                None => break,
+                Some(entry) => evicted.push(entry)
+            }
         }
+        evicted
     }
 
-    pub fn reset(&mut self) {
-        self.map.clear();
-        self.head = None;
-        self.tail = None;
+    fn evict_node<Q: ?Sized + Hash + Eq>(&mut self, key: &Q, op: AuditOp) -> Option<(K, V)>
+    where
+        K: Borrow<Q>
+    {
+        self.take_node(key, op).map(|node| (node.key, node.value))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // unlinks and removes a node with all its bookkeeping, handing back the node itself
+    fn take_node<Q: ?Sized + Hash + Eq>(&mut self, key: &Q, op: AuditOp) -> Option<LruNode<K, V>>
+    where
+        K: Borrow<Q>
+    {
+        let slot = self.map.remove(key)?;
+        let node = self.detach(slot)?;
+        self.weight = self.weight.saturating_sub(node.weight);
+        if let Some(id) = node.expiry_id {
+            self.expiries.cancel(id);
+        }
+        if let Some(index) = &mut self.index {
+            index.remove(&node.key, &node.value);
+        }
+        if let Some(filter) = &mut self.filter {
+            if filter.removed() {
+                filter.rebuild(self.map.keys());
+            }
+        }
+        self.audit(op, Some(&node.key), AuditOutcome::Removed);
+        Some(node)
+    }
 
-    #[test]
-    fn test_lru_cache() {
-        let mut cache = LruCache::new(2);
-        cache.put(1, 1);
-        cache.put(2, 2);
-        assert_eq!(cache.get(1), Some(1));
-        cache.put(3, 3);
-        assert_eq!(cache.get(2), None);
-        cache.put(4, 4);
-        assert_eq!(cache.get(1), None);
-        assert_eq!(cache.get(3), Some(3));
-        assert_eq!(cache.get(4), Some(4));
+    // stores a node that isn't in the cache yet as the most recently used
+    fn attach(&mut self, node: LruNode<K, V>) {
+        let key = node.key.clone();
+        let slot = self.nodes.insert(node);
+        self.map.insert(key, slot);
+        self.link_back(slot);
     }
 
-    #[test]
-    fn test_lru_cache_delete() {
-        let mut cache = LruCache::new(2);
-        cache.put(1, 1);
-        cache.put(2, 2);
-        cache.delete(1);
-        assert_eq!(cache.get(1), None);
-        assert_eq!(cache.get(2), Some(2));
+    // unlinks the node in `slot`, whose key is already out of the map, and frees the slot. None if it was free
+    fn detach(&mut self, slot: u32) -> Option<LruNode<K, V>> {
+        self.unlink(slot);
+        self.nodes.remove(slot)
     }
 
-    #[test]
-    fn test_lru_cache_reset() {
-        let mut cache = LruCache::new(2);
-        cache.put(1, 1);
-        cache.put(2, 2);
-        cache.reset();
-        assert_eq!(cache.get(1), None);
-        assert_eq!(cache.get(2), None);
+    fn relink_back(&mut self, slot: u32) {
+        if self.tail == Some(slot) {
+            return;
+        }
+        self.unlink(slot);
+        self.link_back(slot);
     }
 
-    #[test]
-    fn test_lru_with_complex_values() {
-        let mut cache = LruCache::new(2);
+    // links to slots out of bounds are skipped rather than followed
+    fn unlink(&mut self, slot: u32) {
+        let Some(node) = self.nodes.get_mut(slot) else { return };
+        let (prev, next) = (node.prev.take(), node.next.take());
+        match prev.and_then(|prev| self.nodes.get_mut(prev)) {
+            None => self.head = next,
+            Some(prev_node) => prev_node.next = next
+        }
+        match next.and_then(|next| self.nodes.get_mut(next)) {
+            None => self.tail = prev,
+            Some(next_node) => next_node.prev = prev
+        }
+    }
+
+    // the node in `slot` must not be linked in already
+    fn link_back(&mut self, slot: u32) {
+        let prev = match self.tail.and_then(|tail| self.nodes.get_mut(tail)) {
+            None => {
+                self.head = Some(slot);
+                None
+            },
+            Some(tail_node) => {
+                tail_node.next = Some(slot);
+                self.tail
+            }
+        };
+        if let Some(node) = self.nodes.get_mut(slot) {
+            node.prev = prev;
+            node.next = None;
+        }
+        self.tail = Some(slot);
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.evict_node(key, AuditOp::Delete).map(|(_, value)| value)
+    }
+
+    pub fn invalidate_where<F: FnMut(&K, &V) -> bool>(&mut self, mut predicate: F) -> usize {
+        let now = Instant::now();
+        // expired entries aren't offered to the predicate or counted, they are purged on the way
+        let mut expired = Vec::new();
+        let mut keys = Vec::new();
+        for node in self.nodes.iter() {
+            if node.is_expired(now) {
+                expired.push(node.key.clone());
+            } else if predicate(&node.key, &node.value) {
+                keys.push(node.key.clone());
+            }
+        }
+
+        for key in &expired {
+            self.evict_node(key, AuditOp::Expire);
+        }
+        for key in &keys {
+            self.evict_node(key, AuditOp::Invalidate);
+        }
+        keys.len()
+    }
+
+    // removes the live entries for `keys` in one pass, e.g. to fan out invalidation events, and returns how many
+    // were present
+    pub fn invalidate_many<I: IntoIterator<Item = K>>(&mut self, keys: I) -> usize {
+        let now = Instant::now();
+        let mut invalidated = 0;
+        for key in keys {
+            let Some(node) = self.node(&key) else { continue };
+            if node.is_expired(now) {
+                self.evict_node(&key, AuditOp::Expire);
+            } else {
+                self.evict_node(&key, AuditOp::Invalidate);
+                invalidated += 1;
+            }
+        }
+        invalidated
+    }
+
+    // drops entries written more than `age` ago however recently they were read, e.g. everything cached
+    // before an upstream data change. returns how many live entries were dropped
+    pub fn evict_older_than(&mut self, age: Duration) -> usize {
+        let now = Instant::now();
+        let Some(cutoff) = now.checked_sub(age) else { return 0 };
+        // expired entries are purged on the way but not counted, like in invalidate_where
+        let mut expired = Vec::new();
+        let mut keys = Vec::new();
+        for node in self.nodes.iter().filter(|node| node.written_at < cutoff) {
+            if node.is_expired(now) {
+                expired.push(node.key.clone());
+            } else {
+                keys.push(node.key.clone());
+            }
+        }
+
+        for key in &expired {
+            self.evict_node(key, AuditOp::Expire);
+        }
+        for key in &keys {
+            self.evict_node(key, AuditOp::Invalidate);
+        }
+        keys.len()
+    }
+
+    // drops every entry whose value maps to `index`, e.g. all sessions of one user.
+    // requires index_by, without it nothing is dropped
+    pub fn invalidate_by_index<I: Hash + ?Sized>(&mut self, index: &I) -> usize {
+        let keys = match &mut self.index {
+            None => return 0,
+            Some(value_index) => value_index.take(index)
+        };
+        keys.iter().filter(|key| self.evict_node(key, AuditOp::Invalidate).is_some()).count()
+    }
+
+    // only visits entries whose deadline has passed
+    pub fn purge_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let mut purged = 0;
+        while let Some(key) = self.expiries.pop_due(now) {
+            let Some(node) = self.node(&key) else { continue };
+            let deadline = node.deadline();
+
+            match deadline {
+                Some(deadline) if deadline > now => {
+                    // idle deadline was pushed back by a read since it was scheduled
+                    let id = self.expiries.schedule(key.clone(), deadline);
+                    if let Some(node) = self.node_mut(&key) {
+                        node.expiry_id = Some(id);
+                    }
+                },
+                _ => {
+                    if let Some(node) = self.node_mut(&key) {
+                        node.expiry_id = None;
+                    }
+                    self.evict_node(&key, AuditOp::Expire);
+                    purged += 1;
+                }
+            }
+        }
+        purged
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let head = self.nodes.get(self.head?)?.key.clone();
+        self.evict_node(&head, AuditOp::Evict)
+    }
+
+    // evicts the least recently used fraction of the cache configured for `level`, so the cache gives memory back
+    // before the process is killed for it. returns what was evicted
+    pub fn on_pressure(&mut self, level: MemoryPressure) -> Vec<(K, V)> {
+        let fraction = match level {
+            MemoryPressure::Normal => return Vec::new(),
+            MemoryPressure::Moderate => self.pressure_trim.0,
+            MemoryPressure::Critical => self.pressure_trim.1
+        };
+        let keep = 1.0 - fraction;
+        let (len, weight) = ((self.map.len() as f64 * keep) as usize, (self.weight as f64 * keep) as usize);
+        let mut evicted = self.trim_to_len(len);
+        evicted.extend(self.trim_to_weight(weight));
+        evicted
+    }
+
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim_to_len(capacity);
+    }
+
+    // returns the entries evicted to fit the new capacity
+    pub fn try_resize(&mut self, capacity: usize) -> Result<Vec<(K, V)>, CacheError> {
+        if capacity == 0 {
+            return Err(CacheError::ZeroCapacity);
+        }
+        self.capacity = capacity;
+        Ok(self.trim_to_len(capacity))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn current_weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn max_weight(&self) -> usize {
+        self.max_weight
+    }
+
+    // counters since the cache was created, along with its current size
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.map.len(),
+            capacity: self.capacity,
+            weight: self.weight,
+            max_weight: self.max_weight,
+            ..self.stats
+        }
+    }
+
+    // hits and misses over the last 1, 5 and 15 minutes. all zero unless track_windowed_stats was set
+    pub fn windowed_stats(&self) -> WindowedStats {
+        self.windows.as_ref().map_or_else(WindowedStats::default, |windows| windows.stats())
+    }
+
+    // how much more weight fits before anything gets evicted
+    pub fn headroom(&self) -> usize {
+        self.max_weight.saturating_sub(self.weight)
+    }
+
+    // folds `other`'s live entries in, least recently used first, so its most recent entries end up most recent here.
+    // keys in both caches get `resolve(key, mine, theirs)`. entries keep their remaining ttl, those without one get
+    // this cache's defaults. returns what was evicted to make room
+    pub fn merge<F: FnMut(&K, &V, V) -> V>(&mut self, mut other: LruCache<K, V>, mut resolve: F) -> Vec<(K, V)> {
+        let now = Instant::now();
+        let mut evicted = Vec::new();
+        for slot in other.slots_in_order() {
+            let Some(node) = other.nodes.remove(slot) else { continue };
+            if node.is_expired(now) {
+                continue;
+            }
+            let key = node.key;
+            let ttl = node.expires_at.map(|deadline| deadline.saturating_duration_since(now));
+            let options = EntryOptions { ttl, tti: node.tti }.or(self.defaults);
+            let value = match self.peek(&key) {
+                None => node.value,
+                Some(mine) => resolve(&key, &mine, node.value)
+            };
+            evicted.extend(self.insert(key, value, options).1);
+        }
+        evicted
+    }
+
+    // moves the live entries matching `predicate` into a new cache of the same capacity, default expiry and
+    // weigher (with its limits), in the same relative order and with their metadata and weights. the other builder
+    // settings stay here
+    pub fn split_off_where<F: FnMut(&K, &V) -> bool>(&mut self, mut predicate: F) -> LruCache<K, V> {
+        let now = Instant::now();
+        let keys: Vec<K> = self.keys_in_order().into_iter()
+            .filter(|key| self.node(key).is_some_and(|node| !node.is_expired(now) && predicate(key, &node.value)))
+            .collect();
+
+        let mut split = LruCache::with_options(self.capacity, self.defaults);
+        split.weigher = self.weigher.clone();
+        split.max_weight = self.max_weight;
+        split.max_entry_weight = self.max_entry_weight;
+        split.next_seq = self.next_seq;
+        split.next_version = self.next_version;
+        for key in keys {
+            let Some(mut node) = self.take_node(&key, AuditOp::Split) else { continue };
+            node.expiry_id = node.deadline().map(|deadline| split.expiries.schedule(key.clone(), deadline));
+            split.weight += node.weight;
+            split.attach(node);
+        }
+        split
+    }
+
+    // compares the live entries of two caches, ignoring recency and metadata
+    pub fn diff(&self, other: &LruCache<K, V>) -> CacheDiff<K>
+    where
+        V: PartialEq
+    {
+        let mut diff = CacheDiff { only_in_self: Vec::new(), only_in_other: Vec::new(), changed: Vec::new() };
+        for (key, value) in self.entries() {
+            match other.peek(&key) {
+                None => diff.only_in_self.push(key),
+                Some(theirs) if theirs != value => diff.changed.push(key),
+                Some(_) => ()
+            }
+        }
+        diff.only_in_other = other.entries().into_iter()
+            .filter(|(key, _)| !self.contains_key(key))
+            .map(|(key, _)| key)
+            .collect();
+        diff
+    }
+
+    // an immutable, Sync copy for sharing between threads, thaw turns it back into this cache
+    pub fn freeze(self) -> FrozenLruCache<K, V> {
+        FrozenLruCache::new(self)
+    }
+
+    pub fn reset(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.expiries.clear();
+        if let Some(index) = &mut self.index {
+            index.clear();
+        }
+        if let Some(filter) = &mut self.filter {
+            filter.rebuild(self.map.keys());
+        }
+        if let Some(growth) = &mut self.growth {
+            growth.clear();
+        }
+        self.weight = 0;
+        self.head = None;
+        self.tail = None;
+        self.audit(AuditOp::Reset, None, AuditOutcome::Removed);
+    }
+}
+
+// one line for logs: `len 12/100, weight 12, hit rate 93.00%, oldest 4.2s`
+impl<K: Clone + Eq + Hash, V: Clone> fmt::Display for LruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "len {}/{}, weight {}", self.map.len(), self.capacity, self.weight)?;
+        if self.max_weight != usize::MAX {
+            write!(f, "/{}", self.max_weight)?;
+        }
+        write!(f, ", hit rate {:.2}%", self.stats.hit_rate() * 100.0)?;
+        match self.nodes.iter().map(|node| node.inserted_at).min() {
+            None => write!(f, ", oldest -"),
+            Some(inserted_at) => write!(f, ", oldest {:.1?}", inserted_at.elapsed())
+        }
+    }
+}
+
+// live entries, least recently used first
+impl<K: Clone + Eq + Hash + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for LruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.entries()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_cache() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.get(&1), Some(1));
+        cache.put(3, 3);
+        assert_eq!(cache.get(&2), None);
+        cache.put(4, 4);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&3), Some(3));
+        assert_eq!(cache.get(&4), Some(4));
+    }
+
+    #[test]
+    fn test_lru_cache_borrowed_keys() {
+        let mut cache = LruCache::new(2).admission(TinyLfu::new(16));
+        cache.put("a".to_string(), 1);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.peek("a"), Some(1));
+        assert!(cache.contains_key("a"));
+        assert!(cache.update("a", |value| *value += 1));
+        assert_eq!(cache.compare_and_swap("a", &2, 3), Ok(()));
+        assert!(cache.update_if("a", |value| Some(value + 1)));
+        assert_eq!(cache.remove_if("a", |value| *value == 4), Some(4));
+        assert_eq!(cache.get("a"), None);
+
+        // misses by a borrowed form still count towards admission
+        cache.put("b".to_string(), 2);
+        cache.put("c".to_string(), 3);
+        cache.get("d");
+        cache.get("d");
+        cache.put("d".to_string(), 4);
+        assert_eq!(cache.get("d"), Some(4));
+        cache.delete("d");
+        assert_eq!(cache.len(), 1);
+
+        let mut any = AnyLruCache::new(2);
+        any.put("a".to_string(), 1u32);
+        assert_eq!(any.get::<u32, _>("a").as_deref(), Some(&1));
+        assert_eq!(any.remove::<u32, _>("a").as_deref(), Some(&1));
+    }
+
+    #[test]
+    fn test_lru_cache_promotes_middle_entry() {
+        let mut cache = LruCache::new(3);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        assert_eq!(cache.get(&2), Some(2));
+        cache.delete(&3);
+        cache.put(4, 4);
+        cache.put(5, 5);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(2));
+        assert_eq!(cache.get(&4), Some(4));
+        assert_eq!(cache.get(&5), Some(5));
+    }
+
+    #[test]
+    fn test_lru_cache_push_returns_evicted() {
+        let mut cache = LruCache::new(1);
+        assert_eq!(cache.push(1, 1), vec![]);
+        assert_eq!(cache.push(1, 2), vec![]);
+        assert_eq!(cache.push(2, 2), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_lru_cache_weighted() {
+        let mut cache = LruCache::new(10).weigh_by(10, |_, value: &Vec<u8>| value.len());
+        cache.put(1, vec![0; 4]);
+        cache.put(2, vec![0; 4]);
+        assert_eq!(cache.current_weight(), 8);
+        assert_eq!(cache.headroom(), 2);
+        cache.get(&1);
+        assert_eq!(cache.push(3, vec![0; 5]), vec![(2, vec![0; 4])]);
+        assert_eq!(cache.current_weight(), 9);
+        cache.put(1, vec![0; 1]);
+        assert_eq!(cache.current_weight(), 6);
+        assert_eq!(cache.entry_info(&1).unwrap().weight, 1);
+        assert_eq!(cache.max_weight(), 10);
+    }
+
+    #[test]
+    fn test_lru_cache_admission() {
+        struct RejectOdd;
+
+        impl AdmissionPolicy<i32> for RejectOdd {
+            fn admit(&mut self, candidate: &i32, _victim: &i32) -> bool {
+                candidate % 2 == 0
+            }
+        }
+
+        let mut lru = LruCache::new(2).admission(RejectOdd);
+        lru.put(1, 1);
+        lru.put(3, 3);
+        assert_eq!(lru.push(5, 5), vec![(5, 5)]);
+        assert_eq!(lru.push(4, 4), vec![(1, 1)]);
+        assert_eq!(lru.push(3, 33), vec![]);
+        assert_eq!(lru.get(&3), Some(33));
+    }
+
+    #[test]
+    fn test_lru_cache_bloom_filter() {
+        let mut lru = LruCache::new(2).bloom_filter(0.01);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.put(3, 3);
+        assert!(!lru.contains_key(&1));
+        assert!(lru.contains_key(&3));
+        assert_eq!(lru.get(&2), Some(2));
+        assert_eq!(lru.get(&4), None);
+
+        lru.delete(&2);
+        lru.delete(&3);
+        assert!(lru.is_empty());
+        lru.put(1, 1);
+        assert_eq!(lru.peek(&1), Some(1));
+    }
+
+    #[test]
+    fn test_lru_cache_survives_dangling_links() {
+        let mut lru = LruCache::new(2);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.nodes[0].next = Some(7);
+        lru.tail = Some(8);
+        assert_eq!(lru.entries(), vec![(1, 1)]);
+        lru.put(3, 3);
+        lru.get(&2);
+        lru.delete(&7);
+        lru.resize(0);
+        lru.trim_to_len(0);
+        lru.reset();
+        assert_eq!(lru.push(4, 4), vec![(4, 4)]);
+        lru.resize(1);
+        lru.put(4, 4);
+        assert_eq!(lru.entries(), vec![(4, 4)]);
+    }
+
+    #[test]
+    fn test_lru_cache_oversized_values() {
+        let weigh = |_: &i32, value: &usize| *value;
+        let mut lru = LruCache::new(10).weigh_by(10, weigh);
+        lru.put(1, 5);
+        lru.put(2, 5);
+        assert_eq!(lru.push(2, 20), vec![(2, 20)]);
+        assert_eq!(lru.entries(), vec![(1, 5)]);
+
+        let mut lru = LruCache::new(10).weigh_by(10, weigh).oversized(OversizedPolicy::Reject);
+        lru.put(1, 5);
+        assert_eq!(lru.push(1, 20), vec![(1, 20)]);
+        assert_eq!(lru.get(&1), Some(5));
+
+        let mut lru = LruCache::new(10).weigh_by(10, weigh).oversized(OversizedPolicy::Bypass);
+        lru.put(1, 5);
+        lru.put(2, 5);
+        assert_eq!(lru.push(1, 20), vec![]);
+        assert_eq!(lru.entries(), vec![(2, 5)]);
+
+        let mut lru = LruCache::new(10).weigh_by(10, weigh).max_entry_weight(4);
+        lru.put(1, 3);
+        assert_eq!(lru.push(2, 5), vec![(2, 5)]);
+        assert_eq!(lru.try_put(2, 5), Err(CacheError::ValueTooHeavy { weight: 5, max_weight: 4 }));
+        assert_eq!(lru.entries(), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_lru_cache_try_variants() {
+        assert_eq!(LruCache::<i32, i32>::try_new(0).err(), Some(CacheError::ZeroCapacity));
+        let mut lru = LruCache::try_new(2).unwrap().weigh_by(10, |_, value: &usize| *value);
+        assert_eq!(lru.try_push(1, 4), Ok(vec![]));
+        assert_eq!(lru.try_put(2, 11), Err(CacheError::ValueTooHeavy { weight: 11, max_weight: 10 }));
+        assert_eq!(lru.try_resize(0), Err(CacheError::ZeroCapacity));
+        assert_eq!(lru.get(&1), Some(4));
+
+        let mut lru = LruCache::new(1).admission(TinyLfu::new(16));
+        lru.put(1, 1);
+        lru.get(&1);
+        assert_eq!(lru.try_put(2, 2), Err(CacheError::Rejected));
+    }
+
+    #[test]
+    fn test_lru_cache_peek_keeps_order() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.peek(&1), Some(1));
+        cache.put(3, 3);
+        assert_eq!(cache.peek(&1), None);
+    }
+
+    #[test]
+    fn test_lru_cache_delete() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.delete(&1);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(2));
+    }
+
+    #[test]
+    fn test_lru_cache_invalidate_where() {
+        let mut cache = LruCache::new(4);
+        cache.put(1, 10);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        assert_eq!(cache.invalidate_where(|key, value| *key == 1 || *value == 30), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&2), Some(20));
+        cache.put(4, 40);
+        cache.put(5, 50);
+        cache.put(6, 60);
+        cache.put(7, 70);
+        assert_eq!(cache.get(&2), None);
+    }
+
+    #[test]
+    fn test_lru_cache_invalidate_many() {
+        let mut cache = LruCache::new(4);
+        cache.put(1, 10);
+        cache.put(2, 20);
+        cache.put_with_ttl(3, 30, Duration::ZERO);
+        cache.put(4, 40);
+        assert_eq!(cache.invalidate_many([1, 3, 4, 4, 5]), 2);
+        assert_eq!(cache.entries(), vec![(2, 20)]);
+    }
+
+    #[test]
+    fn test_lru_cache_invalidate_where_skips_expired() {
+        let mut cache = LruCache::new(4);
+        cache.put(1, 10);
+        cache.put_with_ttl(2, 20, Duration::ZERO);
+        cache.put_with_ttl(3, 30, Duration::ZERO);
+        assert_eq!(cache.invalidate_where(|_, value| *value >= 20), 0);
+        assert_eq!(cache.invalidate_where(|_, _| true), 1);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_lru_cache_ttl() {
+        let mut cache = LruCache::new(3);
+        cache.put_with_ttl(1, 1, Duration::ZERO);
+        cache.put_with_ttl(2, 2, Duration::from_secs(60));
+        cache.put(3, 3);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.len(), 2);
+        let (value, remaining) = cache.get_with_expiry(&2).unwrap();
+        assert_eq!(value, 2);
+        assert!(remaining.unwrap() <= Duration::from_secs(60));
+        assert_eq!(cache.get_with_expiry(&3), Some((3, None)));
+    }
+
+    #[test]
+    fn test_lru_cache_ttl_past_instant_range() {
+        let mut cache = LruCache::with_options(3, EntryOptions::tti(Duration::MAX));
+        cache.put_with_ttl(1, 1, Duration::MAX);
+        cache.put(2, 2);
+        assert_eq!(cache.get_with_expiry(&1), Some((1, None)));
+        assert_eq!(cache.get_with_expiry(&2), Some((2, None)));
+        cache.put_with_ttl(3, 3, Duration::ZERO);
+        assert!(!cache.touch_with_ttl(&3, Duration::MAX));
+        assert!(cache.touch_with_ttl(&2, Duration::MAX));
+        assert_eq!(cache.get_stale(&2, Duration::MAX), Some((2, false)));
+        cache.put_with_ttl(4, 4, Duration::ZERO);
+        assert_eq!(cache.get_stale(&4, Duration::MAX), Some((4, true)));
+    }
+
+    #[test]
+    fn test_lru_cache_touch_with_ttl() {
+        let mut cache = LruCache::new(2);
+        cache.put_with_ttl(1, 1, Duration::ZERO);
+        cache.put_with_ttl(2, 2, Duration::from_secs(5));
+        cache.put(3, 3);
+        assert!(!cache.touch_with_ttl(&1, Duration::from_secs(60)));
+        assert!(cache.touch_with_ttl(&2, Duration::from_secs(60)));
+        assert!(cache.time_to_live(&2).unwrap() > Duration::from_secs(5));
+        // 2 is most recently used now
+        cache.put(4, 4);
+        assert_eq!(cache.peek(&3), None);
+        assert!(cache.touch_with_ttl(&4, Duration::ZERO));
+        assert_eq!(cache.get(&4), None);
+    }
+
+    #[test]
+    fn test_lru_cache_ttl_and_tti() {
+        let mut cache = LruCache::with_options(3, EntryOptions::tti(Duration::from_secs(60)));
+        cache.put_with_options(1, 1, EntryOptions::ttl(Duration::from_secs(5)));
+        cache.put_with_options(2, 2, EntryOptions::tti(Duration::ZERO));
+        cache.put(3, 3);
+        let (_, remaining) = cache.get_with_expiry(&1).unwrap();
+        assert!(remaining.unwrap() <= Duration::from_secs(5));
+        assert_eq!(cache.get(&2), None);
+        let (_, remaining) = cache.get_with_expiry(&3).unwrap();
+        assert!(remaining.unwrap() > Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_lru_cache_value_expiry() {
+        #[derive(Clone)]
+        struct Token(Option<Instant>);
+
+        impl Expiry for Token {
+            fn expires_at(&self) -> Option<Instant> {
+                self.0
+            }
+        }
+
+        let mut cache = LruCache::with_ttl(10, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.put_expiring("expired", Token(Some(now)));
+        cache.put_expiring("default", Token(None));
+        cache.put_expiring("long", Token(Some(now + Duration::from_secs(3600))));
+        assert!(cache.get(&"expired").is_none());
+        assert!(cache.time_to_live(&"default").unwrap() <= Duration::from_secs(60));
+        assert!(cache.time_to_live(&"long").unwrap() > Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_lru_cache_entry_info() {
+        let mut cache = LruCache::new(2);
+        cache.put_with_ttl(1, 1, Duration::from_secs(60));
+        cache.get(&1);
+        cache.get(&1);
+        cache.put(1, 2);
+        let info = cache.entry_info(&1).unwrap();
+        assert_eq!(info.access_count, 2);
+        assert_eq!(info.weight, 1);
+        assert!(info.last_accessed >= info.inserted_at);
+        assert_eq!(info.expires_at, None);
+        assert_eq!(cache.entry_info(&2), None);
+    }
+
+    #[test]
+    fn test_lru_cache_hot_and_cold_keys() {
+        let mut cache = LruCache::new(4);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        cache.put(4, 4);
+        for _ in 0..3 {
+            cache.get(&2);
+        }
+        cache.get(&4);
+        cache.get(&3);
+        assert_eq!(cache.top_n_by_hits(2), vec![(2, 3), (3, 1)]);
+        assert_eq!(cache.coldest_n(2), vec![(1, 0), (4, 1)]);
+        assert_eq!(cache.coldest_n(10).len(), 4);
+    }
+
+    #[test]
+    fn test_lru_cache_heavy_hitters() {
+        let mut lru = LruCache::new(2).track_heavy_hitters(10);
+        lru.put(1, 1);
+        lru.get(&1);
+        lru.get(&2);
+        lru.put(2, 2);
+        lru.get(&2);
+        lru.get(&2);
+        assert_eq!(lru.heavy_hitters(1), vec![(2, 3)]);
+        // a key that is never cached still shows up, here looked up by a borrowed form
+        let mut lru = LruCache::<String, i32>::new(2).track_heavy_hitters(10);
+        lru.put("cached".to_string(), 1);
+        for _ in 0..3 {
+            lru.get("missing");
+        }
+        lru.get("cached");
+        assert_eq!(lru.heavy_hitters(1), vec![("missing".to_string(), 3)]);
+        assert_eq!(LruCache::<i32, i32>::new(2).heavy_hitters(1), vec![]);
+    }
+
+    #[cfg(feature = "audit")]
+    #[test]
+    fn test_lru_cache_audit_log() {
+        let mut lru = LruCache::new(1).audit_log(3);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.get(&1);
+
+        let ops: Vec<(AuditOp, AuditOutcome)> = lru.recent_ops().iter().map(|record| (record.op, record.outcome)).collect();
+        assert_eq!(ops, vec![(AuditOp::Evict, AuditOutcome::Removed), (AuditOp::Put, AuditOutcome::Inserted), (AuditOp::Get, AuditOutcome::Miss)]);
+        assert!(lru.recent_ops()[0].is_for(&1));
+    }
+
+    #[test]
+    fn test_lru_cache_purge_expired() {
+        let mut cache = LruCache::with_ttl(3, Duration::ZERO);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put_with_ttl(3, 3, Duration::from_secs(60));
+        assert_eq!(cache.peek(&1), None);
+        assert_eq!(cache.purge_expired(), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&3), Some(3));
+        cache.put_with_ttl(3, 3, Duration::ZERO);
+        cache.put_with_options(4, 4, EntryOptions { ttl: Some(Duration::from_secs(60)), tti: Some(Duration::from_secs(60)) });
+        cache.get(&4);
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.purge_expired(), 0);
+        assert_eq!(cache.get(&4), Some(4));
+    }
+
+    #[test]
+    fn test_lru_cache_timer_wheel_purge() {
+        let mut cache = LruCache::new(3).expiry_backend(ExpiryBackend::TimerWheel { tick: Duration::from_millis(1) });
+        cache.put_with_ttl(1, 1, Duration::ZERO);
+        cache.put_with_ttl(2, 2, Duration::from_secs(60));
+        cache.put(3, 3);
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_lru_cache_resize() {
+        let mut cache = LruCache::new(3);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        cache.resize(1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&3), Some(3));
+        assert_eq!(cache.pop_lru(), Some((3, 3)));
+        assert_eq!(cache.pop_lru(), None);
+    }
+
+    #[test]
+    fn test_lru_cache_trim() {
+        let mut cache = LruCache::new(10).weigh_by(100, |_, value: &usize| *value);
+        cache.put(1, 10);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        cache.get(&1);
+        assert_eq!(cache.trim_to_weight(45), vec![(2, 20)]);
+        assert_eq!(cache.trim_to_len(1), vec![(3, 30)]);
+        assert_eq!(cache.trim_to_len(5), vec![]);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.capacity(), 10);
+    }
+
+    #[test]
+    fn test_lru_cache_entries() {
+        let mut cache = LruCache::new(3);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put_with_ttl(3, 3, Duration::ZERO);
+        cache.get(&1);
+        assert_eq!(cache.entries(), vec![(2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn test_lru_cache_reset() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.reset();
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), None);
+    }
+
+    #[test]
+    fn test_lru_with_complex_values() {
+        let mut cache = LruCache::new(2);
         cache.put("a", vec![1, 2, 3]);
         cache.put("b", vec![4, 5, 6]);
-        assert_eq!(cache.get("a"), Some(vec![1, 2, 3]));
+        assert_eq!(cache.get(&"a"), Some(vec![1, 2, 3]));
         cache.put("c", vec![7, 8, 9]);
-        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get(&"b"), None);
         cache.put("d", vec![10, 11, 12]);
-        assert_eq!(cache.get("a"), None);
-        assert_eq!(cache.get("c"), Some(vec![7, 8, 9]));
-        assert_eq!(cache.get("d"), Some(vec![10, 11, 12]));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"c"), Some(vec![7, 8, 9]));
+        assert_eq!(cache.get(&"d"), Some(vec![10, 11, 12]));
+    }
+
+    #[test]
+    fn test_lru_cache_insert_outcome() {
+        let mut lru = LruCache::new(2).weigh_by(10, |_, value: &usize| *value);
+        assert_eq!(lru.put(1, 1), InsertOutcome::Inserted);
+        assert_eq!(lru.put(1, 2), InsertOutcome::Updated);
+        assert_eq!(lru.put_with_ttl(2, 1, Duration::from_secs(60)), InsertOutcome::Inserted);
+        assert_eq!(lru.put(3, 11), InsertOutcome::Rejected);
+        let stats = lru.stats();
+        assert_eq!((stats.inserts, stats.updates, stats.rejections), (2, 1, 1));
+
+        // the stale value an oversized put replaces is evicted, the put itself is still a rejection
+        assert_eq!(lru.put(1, 11), InsertOutcome::Rejected);
+        let stats = lru.stats();
+        assert_eq!((stats.inserts, stats.rejections, stats.evictions, stats.len), (2, 2, 1, 1));
+        let mut lru = LruCache::new(2).weigh_by(10, |_, value: &usize| *value).oversized(OversizedPolicy::Bypass);
+        assert_eq!(lru.put(1, 11), InsertOutcome::Rejected);
+        assert_eq!((lru.stats().rejections, lru.stats().evictions), (1, 0));
+    }
+
+    #[test]
+    fn test_lru_cache_stats() {
+        let mut lru = LruCache::new(2);
+        lru.put(1, 1);
+        lru.put(1, 11);
+        lru.put(2, 2);
+        lru.put(3, 3);
+        lru.get(&3);
+        lru.get(&1);
+        let stats = lru.stats();
+        assert_eq!((stats.hits, stats.misses, stats.inserts, stats.updates, stats.evictions), (1, 1, 3, 1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+        assert_eq!(stats.to_csv_row(), format!("1,1,3,1,0,1,0,2,2,2,{}", usize::MAX));
+        assert_eq!(CacheStats::CSV_HEADER.split(',').count(), stats.to_csv_row().split(',').count());
+        #[cfg(feature = "serde")]
+        assert!(stats.to_json().starts_with("{\"hits\":1,\"misses\":1,\"inserts\":3,"));
+
+        assert_eq!(lru.windowed_stats(), WindowedStats::default());
+        let mut tracked = LruCache::new(2).track_windowed_stats();
+        tracked.put(1, 1);
+        tracked.get(&1);
+        tracked.get(&2);
+        assert_eq!(tracked.windowed_stats().last_1m, WindowStats { hits: 1, misses: 1 });
+
+        let display = lru.to_string();
+        assert!(display.starts_with("len 2/2, weight 2, hit rate 50.00%, oldest "), "{}", display);
+        assert_eq!(LruCache::<i32, i32>::new(1).weigh_by(10, |_, _| 1).to_string(), "len 0/1, weight 0/10, hit rate 0.00%, oldest -");
+    }
+
+    #[test]
+    fn test_lru_cache_iter_by_insertion() {
+        let mut lru = LruCache::new(3);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.put(3, 3);
+        lru.get(&1);
+        lru.put(2, 22);
+        lru.put(4, 4);
+        assert_eq!(lru.entries(), vec![(1, 1), (2, 22), (4, 4)]);
+        assert_eq!(lru.iter_by_insertion().collect::<Vec<_>>(), vec![(1, 1), (2, 22), (4, 4)]);
+        lru.get(&1);
+        assert_eq!(lru.entries(), vec![(2, 22), (4, 4), (1, 1)]);
+        assert_eq!(lru.iter_by_insertion().map(|(key, _)| key).collect::<Vec<_>>(), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_lru_cache_invalidate_by_index() {
+        let mut sessions = LruCache::new(3).index_by(|user: &(u32, &str)| user.0);
+        sessions.put("a", (42, "alice"));
+        sessions.put("b", (7, "bob"));
+        sessions.put("c", (42, "alice"));
+        sessions.put("b", (42, "bob"));
+        sessions.put("d", (7, "dave"));
+        assert_eq!(sessions.invalidate_by_index(&42), 2);
+        assert_eq!(sessions.entries(), vec![("d", (7, "dave"))]);
+        assert_eq!(sessions.invalidate_by_index(&42), 0);
+        sessions.delete(&"d");
+        assert_eq!(sessions.invalidate_by_index(&7), 0);
+    }
+
+    #[test]
+    fn test_lru_cache_compare_and_swap() {
+        let mut lru = LruCache::new(2);
+        assert_eq!(lru.compare_and_swap(&1, &0, 1), Err(CasError::Missing));
+        lru.put(1, 1);
+        assert_eq!(lru.compare_and_swap(&1, &0, 2), Err(CasError::Mismatch(1)));
+        assert_eq!(lru.compare_and_swap(&1, &1, 2), Ok(()));
+        assert!(lru.update_if(&1, |value| (*value < 5).then_some(value * 10)));
+        assert!(!lru.update_if(&1, |value| (*value < 5).then_some(value * 10)));
+        assert!(!lru.update_if(&2, |_| Some(0)));
+        assert_eq!(lru.entries(), vec![(1, 20)]);
+        assert_eq!(lru.remove_if(&1, |value| *value == 2), None);
+        assert_eq!(lru.remove_if(&2, |_| true), None);
+        assert_eq!(lru.remove_if(&1, |value| *value == 20), Some(20));
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn test_lru_cache_versions() {
+        let mut lru = LruCache::new(2);
+        lru.put(1, 1);
+        let (_, seen) = lru.get_versioned(&1).unwrap();
+        assert_eq!(lru.get_versioned(&1), Some((1, seen)));
+        lru.put(1, 1);
+        let (_, updated) = lru.get_versioned(&1).unwrap();
+        assert!(updated > seen);
+        lru.delete(&1);
+        lru.put(1, 1);
+        assert!(lru.get_versioned(&1).unwrap().1 > updated);
+        assert_eq!(lru.get_versioned(&2), None);
+    }
+
+    #[test]
+    fn test_lru_cache_put_if_absent() {
+        let mut lru = LruCache::new(2);
+        assert_eq!(lru.put_if_absent(1, 1), None);
+        lru.put(2, 2);
+        assert_eq!(lru.put_if_absent(1, 11), Some(1));
+        lru.put(3, 3);
+        assert_eq!(lru.entries(), vec![(2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_lru_cache_put_if_newer() {
+        let mut lru = LruCache::new(2);
+        lru.put(1, "plain");
+        assert!(lru.put_if_newer(1, "third", 3));
+        assert!(!lru.put_if_newer(1, "second", 2));
+        assert!(!lru.put_if_newer(1, "third again", 3));
+        assert_eq!(lru.get(&1), Some("third"));
+        assert!(lru.put_if_newer(1, "fourth", 4));
+        lru.put(1, "plain");
+        assert!(lru.put_if_newer(1, "first", 1));
+
+        // a write the cache turns away leaves the old value and its stamp alone
+        let mut lru = LruCache::new(2).weigh_by(10, |_, value: &usize| *value).oversized(OversizedPolicy::Reject);
+        assert!(lru.put_if_newer(1, 1, 1));
+        assert!(!lru.put_if_newer(1, 11, 3));
+        assert!(lru.put_if_newer(1, 2, 2));
+        assert_eq!(lru.compare_and_swap(&1, &2, 11), Err(CasError::Rejected));
+        assert!(!lru.update_if(&1, |_| Some(11)));
+        assert_eq!(lru.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_lru_cache_update_in_place() {
+        let mut lru = LruCache::new(2).weigh_by(5, |_, value: &Vec<u8>| value.len());
+        lru.put(1, vec![1]);
+        lru.put(2, vec![2]);
+        assert!(lru.update(&1, |value| value.push(1)));
+        assert_eq!(lru.entries(), vec![(2, vec![2]), (1, vec![1, 1])]);
+        assert_eq!(lru.current_weight(), 3);
+        assert!(lru.update(&1, |value| value.extend([1, 1, 1])));
+        assert_eq!(lru.entries(), vec![(1, vec![1; 5])]);
+        assert!(lru.update(&1, |value| value.push(1)));
+        assert!(lru.is_empty());
+        assert!(!lru.update(&3, |value| value.clear()));
+    }
+
+    #[test]
+    fn test_lru_cache_get_or_default() {
+        let mut words: LruCache<&str, Vec<u32>> = LruCache::new(2);
+        words.get_or_default("a").unwrap().push(1);
+        words.put("b", vec![2]);
+        words.get_or_default("a").unwrap().push(3);
+        words.get_or_default("c").unwrap().push(4);
+        assert_eq!(words.entries(), vec![("a", vec![1, 3]), ("c", vec![4])]);
+        // a hit reads the entry, it doesn't write it
+        let stats = words.stats();
+        assert_eq!((stats.hits, stats.misses, stats.inserts, stats.updates), (1, 2, 3, 0));
+        let version = words.get_versioned(&"a").unwrap().1;
+        words.get_or_default("a").unwrap();
+        assert_eq!(words.get_versioned(&"a").unwrap().1, version);
+
+        // the default gets in whatever the admission policy thinks of it
+        let mut counts = LruCache::new(1).admission(TinyLfu::new(16));
+        counts.put(1, 0);
+        counts.get(&1);
+        *counts.get_or_default(2).unwrap() += 1;
+        assert_eq!(counts.entries(), vec![(2, 1)]);
+
+        let mut none = LruCache::new(2).weigh_by(0, |_, _: &u32| 1);
+        assert_eq!(none.get_or_default(1), Err(CacheError::ValueTooHeavy { weight: 1, max_weight: 0 }));
+    }
+
+    #[test]
+    fn test_lru_cache_append() {
+        let mut lists = LruCache::new(2).weigh_by(6, |_, value: &Vec<u8>| value.len());
+        lists.append(1, [1, 2]);
+        lists.append(2, vec![3]);
+        lists.append(1, [4]);
+        assert_eq!(lists.entries(), vec![(2, vec![3]), (1, vec![1, 2, 4])]);
+        assert_eq!(lists.current_weight(), 4);
+        lists.append(1, [5, 6, 7]);
+        assert_eq!(lists.entries(), vec![(1, vec![1, 2, 4, 5, 6, 7])]);
+
+        let mut words: LruCache<u32, String> = LruCache::new(2);
+        words.append(1, ["a", "b"]);
+        assert_eq!(words.get(&1).as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn test_lru_cache_upsert() {
+        let mut totals = LruCache::new(2);
+        totals.upsert("a", 1, |total, value| total + value);
+        totals.upsert("b", 5, |total, value| total + value);
+        totals.upsert("a", 2, |total, value| total + value);
+        assert_eq!(totals.entries(), vec![("b", 5), ("a", 3)]);
+    }
+
+    #[test]
+    fn test_lru_cache_counters() {
+        let mut requests = LruCache::with_ttl(2, Duration::from_secs(60));
+        assert_eq!(requests.increment("client", 1), 1);
+        let deadline = requests.entry_info(&"client").unwrap().expires_at;
+        assert_eq!(requests.increment("client", 2), 3);
+        assert_eq!(requests.decrement("client", 1), 2);
+        assert_eq!(requests.entry_info(&"client").unwrap().expires_at, deadline);
+        assert_eq!(requests.decrement("other", 1i64), -1);
+    }
+
+    #[test]
+    fn test_lru_cache_on_pressure() {
+        let mut lru = LruCache::new(100);
+        for key in 0..100 {
+            lru.put(key, key);
+        }
+        assert_eq!(lru.on_pressure(MemoryPressure::Normal), vec![]);
+        assert_eq!(lru.on_pressure(MemoryPressure::Moderate).len(), 25);
+        assert_eq!(lru.on_pressure(MemoryPressure::Critical).first(), Some(&(25, 25)));
+        assert_eq!(lru.len(), 37);
+
+        let mut lru = LruCache::new(10).weigh_by(100, |_, value: &usize| *value).trim_on_pressure(0.5, 1.0);
+        lru.put(1, 10);
+        lru.put(2, 80);
+        assert_eq!(lru.on_pressure(MemoryPressure::Moderate), vec![(1, 10), (2, 80)]);
+    }
+
+    #[test]
+    fn test_lru_cache_evict_older_than() {
+        let mut lru = LruCache::new(3);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        std::thread::sleep(Duration::from_millis(20));
+        lru.get(&1);
+        lru.put(2, 22);
+        lru.put(3, 3);
+        assert_eq!(lru.evict_older_than(Duration::from_millis(10)), 1);
+        assert_eq!(lru.entries(), vec![(2, 22), (3, 3)]);
+        assert_eq!(lru.evict_older_than(Duration::from_secs(3600 * 24 * 365 * 1000)), 0);
+    }
+
+    #[test]
+    fn test_lru_cache_evict_older_than_skips_expired() {
+        let mut lru = LruCache::new(3);
+        lru.put_with_options(1, 1, EntryOptions::ttl(Duration::from_millis(10)));
+        lru.put(2, 2);
+        std::thread::sleep(Duration::from_millis(20));
+        lru.put(3, 3);
+        // 1 had already expired, it goes but isn't counted
+        assert_eq!(lru.evict_older_than(Duration::from_millis(10)), 1);
+        assert_eq!(lru.entries(), vec![(3, 3)]);
+        assert_eq!(lru.stats().expirations, 1);
+    }
+
+    #[test]
+    fn test_lru_cache_promote_every() {
+        let mut lru = LruCache::new(2).promote_every(2);
+        lru.put(1, 1);
+        lru.put(2, 2);
+        lru.get(&1);
+        assert_eq!(lru.entries(), vec![(1, 1), (2, 2)]);
+        lru.get(&1);
+        assert_eq!(lru.entries(), vec![(2, 2), (1, 1)]);
+        lru.put(3, 3);
+        assert_eq!(lru.get(&2), None);
+    }
+
+    #[test]
+    fn test_lru_cache_merge() {
+        let mut mine = LruCache::new(3);
+        mine.put("a", 1);
+        mine.put("b", 2);
+        let mut theirs = LruCache::new(3);
+        theirs.put("c", 30);
+        theirs.put_with_ttl("b", 20, Duration::from_secs(60));
+        theirs.put("d", 40);
+        theirs.get(&"c");
+
+        let evicted = mine.merge(theirs, |_, mine, theirs| mine + theirs);
+        assert_eq!(evicted, vec![("a", 1)]);
+        assert_eq!(mine.entries(), vec![("b", 22), ("d", 40), ("c", 30)]);
+        assert!(mine.entry_info(&"b").unwrap().expires_at.is_some());
+    }
+
+    #[test]
+    fn test_lru_cache_split_off_where() {
+        let mut lru = LruCache::new(4);
+        for key in 1..=4 {
+            lru.put(key, key * 10);
+        }
+        lru.get(&2);
+        let mut even = lru.split_off_where(|key, _| key % 2 == 0);
+        assert_eq!(lru.entries(), vec![(1, 10), (3, 30)]);
+        assert_eq!(even.entries(), vec![(4, 40), (2, 20)]);
+        assert_eq!(even.entry_info(&2).unwrap().access_count, 1);
+        assert_eq!(even.capacity(), 4);
+        even.put(6, 60);
+        assert_eq!(even.iter_by_insertion().map(|(key, _)| key).collect::<Vec<_>>(), vec![2, 4, 6]);
+
+        let mut lru = LruCache::new(4).weigh_by(10, |_, value: &usize| *value);
+        lru.put(1, 2);
+        lru.put(2, 3);
+        let mut heavy = lru.split_off_where(|_, value| *value > 2);
+        assert_eq!((lru.current_weight(), heavy.current_weight()), (2, 3));
+        heavy.put(3, 8);
+        assert_eq!(heavy.entries(), vec![(3, 8)]);
+    }
+
+    #[test]
+    fn test_lru_cache_diff() {
+        let mut primary = LruCache::new(4);
+        let mut replica = LruCache::new(4);
+        for key in 1..=3 {
+            primary.put(key, key);
+            replica.put(key, key);
+        }
+        replica.get(&1);
+        assert!(primary.diff(&replica).is_empty());
+
+        primary.put(4, 4);
+        primary.put(2, 22);
+        replica.delete(&3);
+        replica.put(5, 5);
+        let diff = primary.diff(&replica);
+        assert_eq!(diff, CacheDiff { only_in_self: vec![3, 4], only_in_other: vec![5], changed: vec![2] });
+    }
+
+    #[test]
+    fn test_lru_cache_most_and_least_recent() {
+        let mut lru = LruCache::new(4);
+        for key in 1..=4 {
+            lru.put(key, key * 10);
+        }
+        lru.get(&2);
+        assert_eq!(lru.most_recent(2), vec![(2, 20), (4, 40)]);
+        assert_eq!(lru.least_recent(2), vec![(1, 10), (3, 30)]);
+        assert_eq!(lru.least_recent(10).len(), 4);
+        // reading them left the order alone
+        assert_eq!(lru.pop_lru(), Some((1, 10)));
     }
 }
\ No newline at end of file
@@ -0,0 +1,373 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::Cache;
+
+// keys ordered by recency, bottom is the least recent
+struct Stack<K> {
+    order: BTreeMap<u64, K>,
+    stamps: HashMap<K, u64>,
+    next: u64
+}
+
+impl<K: Clone + Eq + Hash> Stack<K> {
+    fn new() -> Self {
+        Stack { order: BTreeMap::new(), stamps: HashMap::new(), next: 0 }
+    }
+
+    fn push_top(&mut self, key: K) {
+        self.remove(&key);
+        self.order.insert(self.next, key.clone());
+        self.stamps.insert(key, self.next);
+        self.next += 1;
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self.stamps.remove(key) {
+            None => false,
+            Some(stamp) => {
+                self.order.remove(&stamp);
+                true
+            }
+        }
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.stamps.contains_key(key)
+    }
+
+    fn bottom(&self) -> Option<&K> {
+        self.order.values().next()
+    }
+
+    fn pop_bottom(&mut self) -> Option<K> {
+        let (_, key) = self.order.pop_first()?;
+        self.stamps.remove(&key);
+        Some(key)
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn clear(&mut self) {
+        self.order.clear();
+        self.stamps.clear();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Lir,
+    Hir,
+    // evicted hir key still on the recency stack, a hit on it proves a short reuse distance
+    NonResident
+}
+
+struct Entry<V> {
+    value: Option<V>,
+    status: Status
+}
+
+// LIRS: ranks keys by reuse distance (inter-reference recency) instead of recency. a small hir set
+// takes new and rarely reused keys, so sequential scans and loops slightly larger than the cache
+// only churn that set while the lir set stays resident
+pub struct LirsCache<K: Clone + Eq + Hash, V> {
+    lir_size: usize,
+    hir_size: usize,
+    lir_count: usize,
+    entries: HashMap<K, Entry<V>>,
+    // recency stack S: every lir key plus recently seen hir keys, resident or not
+    stack: Stack<K>,
+    // resident hir keys in eviction order, queue Q
+    queue: Stack<K>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LirsCache<K, V> {
+    // reserves 1% of the capacity, at least one entry, for the hir set
+    pub fn new(capacity: usize) -> Self {
+        let hir_size = (capacity / 100).max(1);
+        LirsCache::with_sizes(capacity.saturating_sub(hir_size).max(1), hir_size)
+    }
+
+    pub fn with_sizes(lir_size: usize, hir_size: usize) -> Self {
+        LirsCache {
+            lir_size: lir_size.max(1),
+            hir_size: hir_size.max(1),
+            lir_count: 0,
+            entries: HashMap::new(),
+            stack: Stack::new(),
+            queue: Stack::new()
+        }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let (key, entry) = self.entries.get_key_value(key)?;
+        let (key, value) = (key.clone(), entry.value.clone()?);
+        self.access(key);
+        Some(value)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.entries.get(key)?.value.clone()
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entries evicted to make room
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let status = self.entries.get(&key).map(|entry| entry.status);
+        if let Some(Status::Lir | Status::Hir) = status {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.value = Some(value);
+            }
+            self.access(key);
+            return Vec::new();
+        }
+
+        if self.lir_count < self.lir_size && status.is_none() {
+            self.lir_count += 1;
+            self.entries.insert(key.clone(), Entry { value: Some(value), status: Status::Lir });
+            self.stack.push_top(key);
+            return Vec::new();
+        }
+
+        let evicted = self.make_room();
+        if self.stack.contains(&key) {
+            // came back while still on the stack, its reuse distance beats the oldest lir key's
+            self.entries.insert(key.clone(), Entry { value: Some(value), status: Status::Lir });
+            self.stack.push_top(key);
+            self.lir_count += 1;
+            self.demote_bottom();
+        } else {
+            self.entries.insert(key.clone(), Entry { value: Some(value), status: Status::Hir });
+            self.stack.push_top(key.clone());
+            self.queue.push_top(key);
+        }
+        self.limit_non_resident();
+        evicted
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let (key, entry) = self.entries.remove_entry(key)?;
+        if entry.status == Status::Lir {
+            self.lir_count -= 1;
+        }
+        self.stack.remove(&key);
+        self.queue.remove(&key);
+        self.prune();
+        entry.value
+    }
+
+    // resident entries
+    pub fn len(&self) -> usize {
+        self.lir_count + self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lir_size + self.hir_size
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.stack.clear();
+        self.queue.clear();
+        self.lir_count = 0;
+    }
+
+    // a hit on a resident key
+    fn access(&mut self, key: K) {
+        let status = match self.entries.get(&key) {
+            None => return,
+            Some(entry) => entry.status
+        };
+
+        match status {
+            Status::Lir => {
+                let was_bottom = self.stack.bottom() == Some(&key);
+                self.stack.push_top(key);
+                if was_bottom {
+                    self.prune();
+                }
+            },
+            Status::Hir if self.stack.contains(&key) => {
+                self.queue.remove(&key);
+                if let Some(entry) = self.entries.get_mut(&key) {
+                    entry.status = Status::Lir;
+                }
+                self.stack.push_top(key);
+                self.lir_count += 1;
+                self.demote_bottom();
+            },
+            Status::Hir => {
+                self.stack.push_top(key.clone());
+                self.queue.push_top(key);
+            },
+            Status::NonResident => ()
+        }
+    }
+
+    // the oldest lir key becomes a resident hir key, keeping the lir set at its size
+    fn demote_bottom(&mut self) {
+        self.prune();
+        if self.lir_count > self.lir_size {
+            if let Some(bottom) = self.stack.pop_bottom() {
+                if let Some(entry) = self.entries.get_mut(&bottom) {
+                    entry.status = Status::Hir;
+                }
+                self.lir_count -= 1;
+                self.queue.push_top(bottom);
+            }
+        }
+        self.prune();
+    }
+
+    // evicts the oldest resident hir keys once the cache is full
+    fn make_room(&mut self) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.len() >= self.capacity() {
+            let Some(key) = self.queue.pop_bottom() else { break };
+            let value = if self.stack.contains(&key) {
+                self.entries.get_mut(&key).and_then(|entry| {
+                    entry.status = Status::NonResident;
+                    entry.value.take()
+                })
+            } else {
+                self.entries.remove(&key).and_then(|entry| entry.value)
+            };
+            evicted.extend(value.map(|value| (key, value)));
+        }
+        evicted
+    }
+
+    // drops hir keys from the bottom of the stack so it always ends in a lir key
+    fn prune(&mut self) {
+        while let Some(bottom) = self.stack.bottom() {
+            match self.entries.get(bottom).map(|entry| entry.status) {
+                Some(Status::Lir) => break,
+                Some(Status::NonResident) | None => {
+                    let bottom = bottom.clone();
+                    self.stack.pop_bottom();
+                    self.entries.remove(&bottom);
+                },
+                Some(Status::Hir) => {
+                    self.stack.pop_bottom();
+                }
+            }
+        }
+    }
+
+    // non-resident keys are only metadata, but keep the stack from growing without bound
+    fn limit_non_resident(&mut self) {
+        let limit = self.capacity() * 2;
+        if self.stack.len() <= limit {
+            return;
+        }
+        let oldest = self.stack.order.values()
+            .find(|key| self.entries.get(*key).is_some_and(|entry| entry.status == Status::NonResident))
+            .cloned();
+        if let Some(oldest) = oldest {
+            self.stack.remove(&oldest);
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for LirsCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        LirsCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        LirsCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        LirsCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        LirsCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::LruCache;
+
+    fn hits<C: Cache<u32, u32>>(cache: &mut C, trace: &[u32]) -> usize {
+        trace.iter().filter(|&&key| {
+            let hit = cache.get(&key).is_some();
+            if !hit {
+                cache.put(key, key);
+            }
+            hit
+        }).count()
+    }
+
+    #[test]
+    fn test_lirs_cache() {
+        let mut cache = LirsCache::with_sizes(2, 1);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        assert_eq!(cache.push(4, 4), vec![(3, 3)]);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.remove(&4), Some(4));
+        cache.reset();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_lirs_survives_loops() {
+        let trace: Vec<u32> = (0..50).flat_map(|_| 0..12).collect();
+        let lru = hits(&mut LruCache::new(10), &trace);
+        let lirs = hits(&mut LirsCache::with_sizes(9, 1), &trace);
+        assert_eq!(lru, 0);
+        assert!(lirs > trace.len() / 2, "{} hits", lirs);
+    }
+
+    proptest! {
+        #[test]
+        fn test_lirs_stays_bounded(lir in 1..6usize, hir in 1..3usize, ops in proptest::collection::vec((0..3u8, 0..16u32), 0..300)) {
+            let mut cache = LirsCache::with_sizes(lir, hir);
+            for (op, key) in ops {
+                match op {
+                    0 => { cache.get(&key); },
+                    1 => cache.put(key, key),
+                    _ => cache.delete(&key)
+                }
+                prop_assert!(cache.len() <= lir + hir);
+                prop_assert!(cache.stack.len() <= (lir + hir) * 2 + 1);
+                prop_assert!(cache.lir_count <= lir);
+            }
+        }
+    }
+}
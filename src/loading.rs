@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{CacheError, LruCache};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lookup<V> {
+    Fresh(V),
+    // expired but still inside the grace window, a refresh has been started
+    Stale(V),
+    Miss
+}
+
+impl<V> Lookup<V> {
+    pub fn is_stale(&self) -> bool {
+        matches!(self, Lookup::Stale(_))
+    }
+
+    pub fn into_value(self) -> Option<V> {
+        match self {
+            Lookup::Fresh(value) | Lookup::Stale(value) => Some(value),
+            Lookup::Miss => None
+        }
+    }
+}
+
+type Loader<K, V> = Arc<dyn Fn(&K) -> Option<V> + Send + Sync>;
+
+// a thread safe cache that fills misses from a loader
+pub struct LoadingCache<K: Clone + Eq + Hash, V> {
+    cache: Arc<Mutex<LruCache<K, V>>>,
+    loader: Loader<K, V>,
+    refresh_ahead: Option<f64>,
+    grace: Duration,
+    xfetch_beta: Option<f64>,
+    load_times: Arc<Mutex<LruCache<K, Duration>>>,
+    refreshing: Arc<Mutex<HashSet<K>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    closed: AtomicBool
+}
+
+impl<K, V> LoadingCache<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static
+{
+    pub fn new<F>(cache: LruCache<K, V>, loader: F) -> Self
+    where
+        F: Fn(&K) -> Option<V> + Send + Sync + 'static
+    {
+        let load_times = LruCache::new(cache.capacity());
+        LoadingCache {
+            cache: Arc::new(Mutex::new(cache)),
+            loader: Arc::new(loader),
+            refresh_ahead: None,
+            grace: Duration::ZERO,
+            xfetch_beta: None,
+            load_times: Arc::new(Mutex::new(load_times)),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+            workers: Mutex::new(Vec::new()),
+            closed: AtomicBool::new(false)
+        }
+    }
+
+    // once a hit is past this fraction of its ttl, reload it in the background
+    pub fn refresh_ahead(mut self, fraction: f64) -> Self {
+        self.refresh_ahead = Some(fraction);
+        self
+    }
+
+    // serve expired entries for up to `grace` past their deadline while they are reloaded
+    pub fn stale_while_revalidate(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    // probabilistic early expiration (xfetch): hits close to their deadline are refreshed early,
+    // with a chance that grows with how long the value took to load, scaled by `beta` (1.0 is a good default)
+    pub fn xfetch(mut self, beta: f64) -> Self {
+        self.xfetch_beta = Some(beta);
+        self
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.load_through(key).unwrap()
+    }
+
+    // like get, but a loader without a value or a poisoned lock is an error
+    pub fn try_get(&self, key: &K) -> Result<V, CacheError> {
+        self.load_through(key)?.ok_or(CacheError::LoaderFailed)
+    }
+
+    // like get, but never calls the loader inline and reports whether the value is stale
+    pub fn lookup(&self, key: &K) -> Lookup<V> {
+        self.try_lookup(key).unwrap()
+    }
+
+    pub fn put(&self, key: K, value: V) {
+        self.cache.lock().unwrap().put(key, value);
+    }
+
+    pub fn delete(&self, key: &K) {
+        self.cache.lock().unwrap().delete(key);
+    }
+
+    pub fn snapshot(&self) -> Vec<(K, V)> {
+        self.cache.lock().unwrap().entries()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.lock().unwrap().is_empty()
+    }
+
+    // every lock a lookup or load takes maps a poisoned lock to an error
+    fn load_through(&self, key: &K) -> Result<Option<V>, CacheError> {
+        match self.try_lookup(key)? {
+            Lookup::Miss => {
+                let Some(value) = load(&self.loader, &self.load_times, key)? else { return Ok(None) };
+                self.cache.lock().map_err(|_| CacheError::Poisoned)?.put(key.clone(), value.clone());
+                Ok(Some(value))
+            },
+            lookup => Ok(lookup.into_value())
+        }
+    }
+
+    fn try_lookup(&self, key: &K) -> Result<Lookup<V>, CacheError> {
+        let (value, progress, time_to_live) = {
+            let mut cache = self.cache.lock().map_err(|_| CacheError::Poisoned)?;
+            let value = cache.get_stale(key, self.grace);
+            (value, cache.ttl_progress(key), cache.time_to_live(key))
+        };
+
+        Ok(match value {
+            None => Lookup::Miss,
+            Some((value, true)) => {
+                self.refresh(key.clone())?;
+                Lookup::Stale(value)
+            },
+            Some((value, false)) => {
+                let refresh_ahead = match (self.refresh_ahead, progress) {
+                    (Some(fraction), Some(progress)) => progress >= fraction,
+                    _ => false
+                };
+                if refresh_ahead || self.expires_early(key, time_to_live)? {
+                    self.refresh(key.clone())?;
+                }
+                Lookup::Fresh(value)
+            }
+        })
+    }
+
+    fn expires_early(&self, key: &K, time_to_live: Option<Duration>) -> Result<bool, CacheError> {
+        let (beta, time_to_live) = match (self.xfetch_beta, time_to_live) {
+            (Some(beta), Some(time_to_live)) => (beta, time_to_live),
+            _ => return Ok(false)
+        };
+        let load_time = match self.load_times.lock().map_err(|_| CacheError::Poisoned)?.peek(key) {
+            None => return Ok(false),
+            Some(load_time) => load_time
+        };
+
+        let gap = load_time.as_secs_f64() * beta * -random_unit().ln();
+        Ok(gap >= time_to_live.as_secs_f64())
+    }
+
+    fn refresh(&self, key: K) -> Result<(), CacheError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let Some(refresh) = RefreshGuard::start(&self.refreshing, key) else { return Ok(()) };
+
+        let cache = Arc::clone(&self.cache);
+        let loader = Arc::clone(&self.loader);
+        let load_times = Arc::clone(&self.load_times);
+        let worker = thread::spawn(move || {
+            if let Ok(Some(value)) = load(&loader, &load_times, &refresh.key) {
+                cache.lock().unwrap().put(refresh.key.clone(), value);
+            }
+        });
+
+        let mut workers = self.workers.lock().map_err(|_| CacheError::Poisoned)?;
+        workers.retain(|worker| !worker.is_finished());
+        workers.push(worker);
+        Ok(())
+    }
+}
+
+// marks a key as being refreshed until dropped, so a loader that panics doesn't keep the key from ever being
+// refreshed again
+pub(crate) struct RefreshGuard<K: Eq + Hash> {
+    refreshing: Arc<Mutex<HashSet<K>>>,
+    pub(crate) key: K
+}
+
+impl<K: Clone + Eq + Hash> RefreshGuard<K> {
+    // None if the key is already being refreshed
+    pub(crate) fn start(refreshing: &Arc<Mutex<HashSet<K>>>, key: K) -> Option<Self> {
+        let started = refreshing.lock().unwrap_or_else(PoisonError::into_inner).insert(key.clone());
+        started.then(|| RefreshGuard { refreshing: Arc::clone(refreshing), key })
+    }
+}
+
+impl<K: Eq + Hash> Drop for RefreshGuard<K> {
+    fn drop(&mut self) {
+        self.refreshing.lock().unwrap_or_else(PoisonError::into_inner).remove(&self.key);
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> LoadingCache<K, V> {
+    // stops starting background refreshes and waits for the ones in flight to land,
+    // the cache keeps serving (and loading misses inline) afterwards
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> Drop for LoadingCache<K, V> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn load<K, V>(loader: &Loader<K, V>, load_times: &Mutex<LruCache<K, Duration>>, key: &K) -> Result<Option<V>, CacheError>
+where
+    K: Clone + Eq + Hash
+{
+    let started = Instant::now();
+    let value = loader(key);
+    load_times.lock().map_err(|_| CacheError::Poisoned)?.put(key.clone(), started.elapsed());
+    Ok(value)
+}
+
+// uniform in (0, 1], random enough for spreading refreshes
+fn random_unit() -> f64 {
+    let bits = RandomState::new().hash_one(Instant::now()) >> 11;
+    (bits as f64 + 1.0) / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn test_loading_cache_loads_misses() {
+        let cache = LoadingCache::new(LruCache::new(2), |key: &i32| (*key > 0).then_some(key * 10));
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.len(), 1);
+        cache.put(2, 5);
+        assert_eq!(cache.get(&2), Some(5));
+        assert_eq!(cache.try_get(&-1), Err(CacheError::LoaderFailed));
+    }
+
+    #[test]
+    fn test_loading_cache_refresh_ahead() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = LoadingCache::new(LruCache::with_ttl(2, Duration::from_secs(60)), move |_: &i32| {
+            Some(counter.fetch_add(1, Ordering::SeqCst) + 1)
+        }).refresh_ahead(0.0);
+
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.get(&1), Some(1));
+        for _ in 0..100 {
+            if loads.load(Ordering::SeqCst) == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_loading_cache_close_waits_for_refresh() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = LoadingCache::new(LruCache::with_ttl(2, Duration::from_secs(60)), move |_: &i32| {
+            thread::sleep(Duration::from_millis(20));
+            Some(counter.fetch_add(1, Ordering::SeqCst) + 1)
+        }).refresh_ahead(0.0);
+
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.get(&1), Some(1));
+        cache.close();
+        assert!(cache.is_closed());
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.get(&1), Some(2));
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_loading_cache_xfetch() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = LoadingCache::new(LruCache::with_ttl(2, Duration::from_secs(60)), move |_: &i32| {
+            thread::sleep(Duration::from_millis(1));
+            Some(counter.fetch_add(1, Ordering::SeqCst) + 1)
+        }).xfetch(1e9);
+
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.lookup(&1), Lookup::Fresh(1));
+        for _ in 0..100 {
+            if loads.load(Ordering::SeqCst) >= 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(loads.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn test_loading_cache_stale_while_revalidate() {
+        let cache = LoadingCache::new(LruCache::new(2), |_: &i32| Some(2))
+            .stale_while_revalidate(Duration::from_secs(60));
+        cache.cache.lock().unwrap().put_with_ttl(1, 1, Duration::ZERO);
+        assert_eq!(cache.lookup(&1), Lookup::Stale(1));
+        for _ in 0..100 {
+            if cache.lookup(&1) == Lookup::Fresh(2) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(cache.lookup(&1), Lookup::Fresh(2));
+        assert_eq!(cache.lookup(&3), Lookup::Miss);
+    }
+
+    #[test]
+    fn test_loading_cache_refresh_after_panic() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = LoadingCache::new(LruCache::new(2), move |_: &i32| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("loader failed");
+            }
+            Some(2)
+        }).stale_while_revalidate(Duration::from_secs(60));
+        cache.cache.lock().unwrap().put_with_ttl(1, 1, Duration::ZERO);
+        assert_eq!(cache.lookup(&1), Lookup::Stale(1));
+        cache.close();
+        assert!(cache.refreshing.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_loading_cache_try_get_poisoned() {
+        let cache = Arc::new(LoadingCache::new(LruCache::new(2), |key: &i32| Some(*key)));
+        cache.put(1, 1);
+        let poisoner = Arc::clone(&cache);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.load_times.lock().unwrap();
+            panic!("poison the lock");
+        }).join();
+        assert_eq!(cache.try_get(&1), Ok(1));
+        assert_eq!(cache.try_get(&2), Err(CacheError::Poisoned));
+    }
+}
@@ -0,0 +1,255 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
+
+use crate::Cache;
+
+struct Entry<V> {
+    // None once evicted, the history is kept a while longer
+    value: Option<V>,
+    // logical times of the last k references, oldest first
+    history: VecDeque<u64>,
+    evicted_at: u64
+}
+
+// LRU-K: evicts the entry whose k-th most recent reference is oldest. entries referenced fewer than k times
+// go first, least recently used among them, so a burst of one-off keys can't push out keys with a real
+// reuse history. histories of evicted keys are remembered for `capacity` more evictions
+pub struct LruKCache<K: Clone + Eq + Hash, V> {
+    capacity: usize,
+    k: usize,
+    clock: u64,
+    entries: HashMap<K, Entry<V>>,
+    // resident keys by (k-th most recent reference or 0, most recent reference), the first is the victim
+    order: BTreeMap<(u64, u64), K>,
+    // evicted keys with when they were evicted, oldest first
+    retired: VecDeque<(K, u64)>,
+    resident: usize
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruKCache<K, V> {
+    // LRU-2, the usual choice
+    pub fn new(capacity: usize) -> Self {
+        LruKCache::with_k(capacity, 2)
+    }
+
+    pub fn with_k(capacity: usize, k: usize) -> Self {
+        LruKCache {
+            capacity: capacity.max(1),
+            k: k.max(1),
+            clock: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            retired: VecDeque::new(),
+            resident: 0
+        }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let (key, entry) = self.entries.get_key_value(key)?;
+        let (key, value) = (key.clone(), entry.value.clone()?);
+        self.reference(&key);
+        Some(value)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.entries.get(key)?.value.clone()
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entries evicted to make room
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.value.is_some() {
+                entry.value = Some(value);
+                self.reference(&key);
+                return Vec::new();
+            }
+        }
+
+        let mut evicted = Vec::new();
+        while self.resident >= self.capacity {
+            match self.evict() {
+                None => break,
+                Some(entry) => evicted.push(entry)
+            }
+        }
+        let entry = self.entries.entry(key.clone()).or_insert_with(|| Entry { value: None, history: VecDeque::new(), evicted_at: 0 });
+        entry.value = Some(value);
+        self.resident += 1;
+        self.reference(&key);
+        evicted
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let entry = self.entries.remove(key)?;
+        if entry.value.is_some() {
+            self.order.remove(&self.rank(&entry.history));
+            self.resident -= 1;
+        }
+        entry.value
+    }
+
+    // resident entries
+    pub fn len(&self) -> usize {
+        self.resident
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resident == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.retired.clear();
+        self.resident = 0;
+    }
+
+    fn rank(&self, history: &VecDeque<u64>) -> (u64, u64) {
+        let kth = if history.len() == self.k { history.front().copied().unwrap_or(0) } else { 0 };
+        (kth, history.back().copied().unwrap_or(0))
+    }
+
+    // records a reference to a resident key
+    fn reference(&mut self, key: &K) {
+        self.clock += 1;
+        let Some(entry) = self.entries.get(key) else { return };
+        let old_rank = self.rank(&entry.history);
+        self.order.remove(&old_rank);
+
+        let (k, clock) = (self.k, self.clock);
+        let Some(entry) = self.entries.get_mut(key) else { return };
+        entry.history.push_back(clock);
+        if entry.history.len() > k {
+            entry.history.pop_front();
+        }
+        let rank = (if entry.history.len() == k { entry.history[0] } else { 0 }, clock);
+        self.order.insert(rank, key.clone());
+    }
+
+    fn evict(&mut self) -> Option<(K, V)> {
+        let (_, key) = self.order.pop_first()?;
+        let entry = self.entries.get_mut(&key)?;
+        let value = entry.value.take()?;
+        entry.evicted_at = self.clock;
+        self.resident -= 1;
+        self.retired.push_back((key.clone(), self.clock));
+        self.forget_retired();
+        Some((key, value))
+    }
+
+    // keeps at most `capacity` histories of evicted keys
+    fn forget_retired(&mut self) {
+        while self.retired.len() > self.capacity {
+            let Some((key, evicted_at)) = self.retired.pop_front() else { break };
+            if self.entries.get(&key).is_some_and(|entry| entry.value.is_none() && entry.evicted_at == evicted_at) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for LruKCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        LruKCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        LruKCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        LruKCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        LruKCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::LruCache;
+
+    fn hits<C: Cache<u32, u32>>(cache: &mut C, trace: &[u32]) -> usize {
+        trace.iter().filter(|&&key| {
+            let hit = cache.get(&key).is_some();
+            if !hit {
+                cache.put(key, key);
+            }
+            hit
+        }).count()
+    }
+
+    #[test]
+    fn test_lru_k_cache() {
+        let mut cache = LruKCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.get(&1);
+        cache.get(&2);
+        cache.get(&1);
+        assert_eq!(cache.push(3, 3), vec![(2, 2)]);
+        // 3 has a single reference, so it goes before 1 even though it is more recent
+        assert_eq!(cache.push(4, 4), vec![(3, 3)]);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.remove(&4), Some(4));
+        assert_eq!(cache.len(), 1);
+        cache.reset();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_lru_k_resists_scans() {
+        // a hot set read over and over, with a burst of one-off keys after each pass
+        let trace: Vec<u32> = (0..20).flat_map(|pass| (0..8).chain(1000 * (pass + 1)..1000 * (pass + 1) + 8)).collect();
+        let lru = hits(&mut LruCache::new(10), &trace);
+        let lru_k = hits(&mut LruKCache::new(10), &trace);
+        assert_eq!(lru, 0);
+        assert!(lru_k > 100, "{} hits", lru_k);
+    }
+
+    proptest! {
+        #[test]
+        fn test_lru_k_stays_bounded(capacity in 1..8usize, k in 1..4usize, ops in proptest::collection::vec((0..3u8, 0..16u32), 0..300)) {
+            let mut cache = LruKCache::with_k(capacity, k);
+            for (op, key) in ops {
+                match op {
+                    0 => { cache.get(&key); },
+                    1 => cache.put(key, key),
+                    _ => cache.delete(&key)
+                }
+                prop_assert!(cache.len() <= capacity);
+                prop_assert_eq!(cache.order.len(), cache.len());
+                prop_assert!(cache.entries.len() <= capacity * 2);
+            }
+        }
+    }
+}
@@ -1,21 +1,79 @@
-//for demo purposes only
+// simulates cache policies over an access trace, to pick a policy and capacity before deploying
+//
+// usage: lru [--policy P[,P..]|all] [--capacity N[,N..]] [--json] [TRACE]
+// the trace is one key per line, or `op,key` lines with op get, put or delete. read from stdin without TRACE.
+// one policy at one capacity prints a detailed report, anything more a comparison table
 
-use lru::LruCache;
+use std::io::{self, Read};
+use std::process;
 
+use lru::sim::{Policy, compare, comparison_table, parse_trace, to_json};
+
+const USAGE: &str = "usage: lru [--policy lru|lru-2|tinylfu|clock-pro|lirs[,..]|all] [--capacity N[,N..]] [--json] [TRACE]";
+
+struct Args {
+    policies: Vec<Policy>,
+    capacities: Vec<usize>,
+    json: bool,
+    trace: Option<String>
+}
+
+fn parse_policies(value: &str) -> Result<Vec<Policy>, String> {
+    if value == "all" {
+        return Ok(Policy::ALL.to_vec());
+    }
+    value.split(',').map(|name| name.trim().parse()).collect()
+}
+
+fn parse_capacities(value: &str) -> Result<Vec<usize>, String> {
+    value.split(',')
+        .map(|capacity| capacity.trim().parse().map_err(|_| format!("invalid capacity {:?}", capacity)))
+        .collect()
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args { policies: vec![Policy::Lru], capacities: vec![1000], json: false, trace: None };
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--policy" => args.policies = parse_policies(&argv.next().ok_or("--policy needs a value")?)?,
+            "--capacity" => args.capacities = parse_capacities(&argv.next().ok_or("--capacity needs a value")?)?,
+            "--json" => args.json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => args.trace = Some(arg)
+        }
+    }
+    Ok(args)
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args()?;
+    let input = match &args.trace {
+        Some(path) => std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map_err(|error| error.to_string())?;
+            input
+        }
+    };
+
+    let trace = parse_trace(&input)?;
+    let reports = compare(&args.policies, &args.capacities, &trace);
+    match reports.as_slice() {
+        _ if args.json => println!("{}", to_json(&reports)),
+        [report] => println!("{}", report),
+        _ => print!("{}", comparison_table(&reports))
+    }
+    Ok(())
+}
 
 fn main() {
-    let mut lru = LruCache::new(2);
-    lru.put(1, 1);
-    lru.put(2, 2);
-    println!("{:?}", lru.get(1));
-    lru.put(3, 3);
-    println!("{:?}", lru.get(2));
-    lru.put(4, 4);
-    println!("{:?}", lru.get(1));
-    println!("{:?}", lru.get(3));
-    println!("{:?}", lru.get(4));
-    lru.delete(3);
-    println!("{:?}", lru.get(3));
-    lru.reset();
-    println!("{:?}", lru.get(4));
-}
\ No newline at end of file
+    if let Err(error) = run() {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(2);
+    }
+}
@@ -0,0 +1,129 @@
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::LruCache;
+
+// an lru cache of keys that each hold their most recent values, like the last n events per user. both bounds
+// are lru: past `capacity` keys the least recently used key goes with all its values, and past `per_key`
+// values a key drops its oldest one
+pub struct LruMultiMap<K: Clone + Eq + Hash, V> {
+    // weighed by value count, so the weight is the total number of values
+    cache: LruCache<K, VecDeque<V>>,
+    per_key: usize
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruMultiMap<K, V> {
+    pub fn new(capacity: usize, per_key: usize) -> Self {
+        LruMultiMap {
+            cache: LruCache::new(capacity).weigh_by(usize::MAX, |_, values: &VecDeque<V>| values.len()),
+            per_key: per_key.max(1)
+        }
+    }
+
+    // appends `value` to the key's values and promotes the key. returns the value dropped to stay within
+    // `per_key`, if any, and the keys evicted to make room with their values
+    pub fn insert(&mut self, key: K, value: V) -> (Option<V>, Vec<(K, Vec<V>)>) {
+        let per_key = self.per_key;
+        let mut value = Some(value);
+        let mut dropped = None;
+        let appended = self.cache.update(&key, |values| {
+            values.extend(value.take());
+            if values.len() > per_key {
+                dropped = values.pop_front();
+            }
+        });
+        let evicted = match (appended, value) {
+            (false, Some(value)) => self.cache.push(key, VecDeque::from([value])),
+            _ => Vec::new()
+        };
+        (dropped, evicted.into_iter().map(|(key, values)| (key, values.into())).collect())
+    }
+
+    // the key's values oldest first, promoting the key
+    pub fn get<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.get(key).map(Vec::from)
+    }
+
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.peek(key).map(Vec::from)
+    }
+
+    // the key's newest value, promoting the key
+    pub fn latest<Q: ?Sized + Hash + Eq + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.get(key)?.pop_back()
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.cache.contains_key(key)
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.cache.delete(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.remove(key).map(Vec::from)
+    }
+
+    // keys
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // values across all keys
+    pub fn value_count(&self) -> usize {
+        self.cache.current_weight()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multimap_bounds_keys_and_values() {
+        let mut events = LruMultiMap::new(2, 3);
+        for event in 1..=4 {
+            events.insert("alice", event);
+        }
+        assert_eq!(events.get(&"alice"), Some(vec![2, 3, 4]));
+        assert_eq!(events.insert("alice", 5), (Some(2), Vec::new()));
+        assert_eq!(events.latest(&"alice"), Some(5));
+
+        events.insert("bob", 1);
+        events.get(&"alice");
+        let (_, evicted) = events.insert("carol", 1);
+        assert_eq!(evicted, vec![("bob", vec![1])]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events.value_count(), 4);
+        assert_eq!(events.remove(&"alice"), Some(vec![3, 4, 5]));
+        assert_eq!(events.value_count(), 1);
+    }
+}
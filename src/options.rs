@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+// per entry expiration settings, unset fields fall back to the cache defaults
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EntryOptions {
+    // expire this long after the entry was written
+    pub ttl: Option<Duration>,
+    // expire after the entry has not been read for this long
+    pub tti: Option<Duration>
+}
+
+impl EntryOptions {
+    pub fn ttl(ttl: Duration) -> Self {
+        EntryOptions { ttl: Some(ttl), tti: None }
+    }
+
+    pub fn tti(tti: Duration) -> Self {
+        EntryOptions { ttl: None, tti: Some(tti) }
+    }
+
+    pub(crate) fn or(self, defaults: EntryOptions) -> Self {
+        EntryOptions {
+            ttl: self.ttl.or(defaults.ttl),
+            tti: self.tti.or(defaults.tti)
+        }
+    }
+}
+
+// what happens to a value heavier than the cache's whole weight budget
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OversizedPolicy {
+    // returned from push as if evicted straight away, replacing any previous entry for the key
+    #[default]
+    Evict,
+    // dropped, any previous entry for the key stays. try_push reports ValueTooHeavy either way
+    Reject,
+    // not cached and any previous entry for the key is removed, as if written past the cache
+    Bypass
+}
+
+// whether the entry limit stays where it was set or grows when a bigger cache would have hit more
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CapacityPolicy {
+    #[default]
+    Fixed,
+    // grows `grow_by` entries at a time, up to `max`, while most misses are on keys evicted within the last
+    // `grow_by` evictions. never shrinks on its own, resize and on_pressure still do
+    Elastic { max: usize, grow_by: usize }
+}
+
+// low memory signal, e.g. from a cgroup memory.pressure watcher or the platform's low memory notification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryPressure {
+    Normal,
+    Moderate,
+    Critical
+}
@@ -0,0 +1,10 @@
+// what a put did, so callers can treat first writes and overwrites differently. the stats count each kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertOutcome {
+    // the key wasn't cached before
+    Inserted,
+    // an existing entry's value was replaced
+    Updated,
+    // not cached, turned away by the admission or oversized policy
+    Rejected
+}
@@ -0,0 +1,39 @@
+use std::hash::Hash;
+
+use rayon::prelude::*;
+
+use crate::LruCache;
+
+impl<K, V> LruCache<K, V>
+where
+    K: Clone + Eq + Hash + Send,
+    V: Clone + Send
+{
+    // parallel iterators over a snapshot of the live entries
+    pub fn par_iter(&self) -> rayon::vec::IntoIter<(K, V)> {
+        self.entries().into_par_iter()
+    }
+
+    pub fn par_values(&self) -> rayon::vec::IntoIter<V> {
+        self.entries().into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<V>>()
+            .into_par_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_iter_over_snapshot() {
+        let mut cache = LruCache::new(100);
+        for i in 0..100 {
+            cache.put(i, i * 2);
+        }
+        let sum: i32 = cache.par_values().sum();
+        assert_eq!(sum, 9900);
+        assert_eq!(cache.par_iter().filter(|(key, _)| key % 2 == 0).count(), 50);
+    }
+}
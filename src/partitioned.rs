@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::{Cache, LruCache};
+
+type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> usize + Send + Sync>;
+
+pub struct PartitionedCache<N: Clone + Eq + Hash, K: Clone + Eq + Hash, V> {
+    cache: LruCache<(N, K), V>,
+    weigher: Option<Weigher<K, V>>,
+    // each namespace's keys in lru order, with the weight of their values
+    partitions: HashMap<N, LruCache<K, usize>>,
+    quotas: HashMap<N, usize>,
+    weight_quotas: HashMap<N, usize>
+}
+
+impl<N: Clone + Eq + Hash, K: Clone + Eq + Hash, V: Clone> PartitionedCache<N, K, V> {
+    pub fn new(capacity: usize) -> Self {
+        PartitionedCache {
+            cache: LruCache::new(capacity),
+            weigher: None,
+            partitions: HashMap::new(),
+            quotas: HashMap::new(),
+            weight_quotas: HashMap::new()
+        }
+    }
+
+    // bounds the whole cache by weight like LruCache::weigh_by, and weighs entries against the namespaces'
+    // weight quotas. without a weigher every entry weighs 1
+    pub fn weigh_by<F>(mut self, max_weight: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + Sync + 'static,
+        N: 'static,
+        K: 'static,
+        V: 'static
+    {
+        let weigher: Weigher<K, V> = Arc::new(weigher);
+        let shared = Arc::clone(&weigher);
+        self.cache = self.cache.weigh_by(max_weight, move |(_, key), value| shared(key, value));
+        self.weigher = Some(weigher);
+        self
+    }
+
+    // caps how many entries a single namespace may hold, evicting within the namespace. a namespace with a quota
+    // of 0 holds nothing, its puts are turned away
+    pub fn set_quota(&mut self, namespace: N, quota: usize) {
+        self.quotas.insert(namespace.clone(), quota);
+        self.enforce(&namespace);
+    }
+
+    pub fn quota(&self, namespace: &N) -> Option<usize> {
+        self.quotas.get(namespace).copied()
+    }
+
+    // caps the total weight of a single namespace's entries, evicting within the namespace. a value heavier than
+    // the whole quota is turned away
+    pub fn set_weight_quota(&mut self, namespace: N, max_weight: usize) {
+        self.weight_quotas.insert(namespace.clone(), max_weight);
+        self.enforce(&namespace);
+    }
+
+    pub fn weight_quota(&self, namespace: &N) -> Option<usize> {
+        self.weight_quotas.get(namespace).copied()
+    }
+
+    pub fn get(&mut self, namespace: &N, key: &K) -> Option<V> {
+        let value = self.cache.get(&(namespace.clone(), key.clone()))?;
+        if let Some(keys) = self.partitions.get_mut(namespace) {
+            keys.get(key);
+        }
+        Some(value)
+    }
+
+    pub fn put(&mut self, namespace: N, key: K, value: V) {
+        self.push(namespace, key, value);
+    }
+
+    // returns the entries evicted to make room, or the entry itself if its namespace has no room for it
+    pub fn push(&mut self, namespace: N, key: K, value: V) -> Vec<(N, K, V)> {
+        let weight = self.weigher.as_ref().map_or(1, |weigher| weigher(&key, &value));
+        let (quota, max_weight) = self.quotas_of(&namespace);
+        if quota == 0 || weight > max_weight {
+            // the previous value goes too, like LruCache's oversized values
+            self.delete(&namespace, &key);
+            return vec![(namespace, key, value)];
+        }
+        let keys = self.partitions.entry(namespace.clone())
+            .or_insert_with(|| LruCache::new(usize::MAX).weigh_by(usize::MAX, |_, weight| *weight));
+        keys.put(key.clone(), weight);
+        let mut evicted = self.enforce(&namespace);
+
+        for ((evicted_namespace, evicted_key), evicted_value) in self.cache.push((namespace, key), value) {
+            self.forget(&evicted_namespace, &evicted_key);
+            evicted.push((evicted_namespace, evicted_key, evicted_value));
+        }
+        evicted
+    }
+
+    pub fn delete(&mut self, namespace: &N, key: &K) {
+        self.forget(namespace, key);
+        self.cache.delete(&(namespace.clone(), key.clone()));
+    }
+
+    pub fn invalidate_namespace(&mut self, namespace: N) -> usize {
+        let mut keys = match self.partitions.remove(&namespace) {
+            None => return 0,
+            Some(keys) => keys
+        };
+
+        let count = keys.len();
+        while let Some((key, _)) = keys.pop_lru() {
+            self.cache.delete(&(namespace.clone(), key));
+        }
+        count
+    }
+
+    pub fn namespace_len(&self, namespace: &N) -> usize {
+        self.partitions.get(namespace).map_or(0, |keys| keys.len())
+    }
+
+    pub fn namespace_weight(&self, namespace: &N) -> usize {
+        self.partitions.get(namespace).map_or(0, |keys| keys.current_weight())
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+        self.partitions.clear();
+    }
+
+    // entry and weight quota, unbounded when unset
+    fn quotas_of(&self, namespace: &N) -> (usize, usize) {
+        let quota = self.quotas.get(namespace).copied().unwrap_or(usize::MAX);
+        (quota, self.weight_quotas.get(namespace).copied().unwrap_or(usize::MAX))
+    }
+
+    // evicts the namespace's least recently used entries until it is within its quotas, returning them
+    fn enforce(&mut self, namespace: &N) -> Vec<(N, K, V)> {
+        let (quota, max_weight) = self.quotas_of(namespace);
+        let Some(keys) = self.partitions.get_mut(namespace) else { return Vec::new() };
+        let mut stale = keys.trim_to_len(quota);
+        stale.extend(keys.trim_to_weight(max_weight));
+        if keys.is_empty() {
+            self.partitions.remove(namespace);
+        }
+
+        let mut evicted = Vec::new();
+        for (key, _) in stale {
+            if let Some(value) = self.cache.remove(&(namespace.clone(), key.clone())) {
+                evicted.push((namespace.clone(), key, value));
+            }
+        }
+        evicted
+    }
+
+    fn forget(&mut self, namespace: &N, key: &K) {
+        if let Some(keys) = self.partitions.get_mut(namespace) {
+            keys.delete(key);
+            if keys.is_empty() {
+                self.partitions.remove(namespace);
+            }
+        }
+    }
+}
+
+impl<N: Clone + Eq + Hash, K: Clone + Eq + Hash, V: Clone> Cache<(N, K), V> for PartitionedCache<N, K, V> {
+    fn get(&mut self, (namespace, key): &(N, K)) -> Option<V> {
+        PartitionedCache::get(self, namespace, key)
+    }
+
+    fn push(&mut self, (namespace, key): (N, K), value: V) -> Vec<((N, K), V)> {
+        PartitionedCache::push(self, namespace, key, value).into_iter()
+            .map(|(namespace, key, value)| ((namespace, key), value))
+            .collect()
+    }
+
+    fn delete(&mut self, (namespace, key): &(N, K)) {
+        PartitionedCache::delete(self, namespace, key)
+    }
+
+    fn reset(&mut self) {
+        PartitionedCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitioned_invalidate_namespace() {
+        let mut cache = PartitionedCache::new(4);
+        cache.put("a", 1, 1);
+        cache.put("a", 2, 2);
+        cache.put("b", 1, 10);
+        assert_eq!(cache.invalidate_namespace("a"), 2);
+        assert_eq!(cache.get(&"a", &1), None);
+        assert_eq!(cache.get(&"b", &1), Some(10));
+        assert_eq!(cache.invalidate_namespace("a"), 0);
+    }
+
+    #[test]
+    fn test_partitioned_quota_evicts_within_namespace() {
+        let mut cache = PartitionedCache::new(10);
+        cache.set_quota("noisy", 2);
+        cache.put("quiet", 1, 1);
+        cache.put("noisy", 1, 1);
+        cache.put("noisy", 2, 2);
+        assert_eq!(cache.get(&"noisy", &1), Some(1));
+        assert_eq!(cache.push("noisy", 3, 3), vec![("noisy", 2, 2)]);
+        assert_eq!(cache.get(&"quiet", &1), Some(1));
+        cache.set_quota("noisy", 1);
+        assert_eq!(cache.namespace_len(&"noisy"), 1);
+        assert_eq!(cache.get(&"noisy", &3), Some(3));
+        assert_eq!(cache.len(), 2);
+
+        cache.set_quota("noisy", 0);
+        assert_eq!(cache.namespace_len(&"noisy"), 0);
+        assert_eq!(cache.push("noisy", 4, 4), vec![("noisy", 4, 4)]);
+        assert_eq!(cache.get(&"noisy", &4), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_partitioned_weight_quota() {
+        let mut cache = PartitionedCache::new(10).weigh_by(100, |_, value: &Vec<u8>| value.len());
+        cache.set_weight_quota("noisy", 5);
+        cache.put("quiet", 1, vec![0; 10]);
+        cache.put("noisy", 1, vec![0; 2]);
+        cache.put("noisy", 2, vec![0; 2]);
+        assert_eq!(cache.push("noisy", 3, vec![0; 3]), vec![("noisy", 1, vec![0; 2])]);
+        assert_eq!(cache.namespace_weight(&"noisy"), 5);
+        assert_eq!(cache.push("noisy", 2, vec![0; 6]), vec![("noisy", 2, vec![0; 6])]);
+        assert_eq!(cache.get(&"noisy", &2), None);
+        assert_eq!(cache.namespace_weight(&"noisy"), 3);
+
+        cache.set_weight_quota("noisy", 2);
+        assert_eq!(cache.namespace_len(&"noisy"), 0);
+        assert_eq!(cache.get(&"quiet", &1), Some(vec![0; 10]));
+    }
+
+    #[test]
+    fn test_partitioned_eviction_updates_namespace() {
+        let mut cache = PartitionedCache::new(2);
+        cache.put("a", 1, 1);
+        cache.put("b", 1, 1);
+        assert_eq!(cache.push("b", 2, 2), vec![("a", 1, 1)]);
+        assert_eq!(cache.namespace_len(&"a"), 0);
+        assert_eq!(cache.namespace_len(&"b"), 2);
+    }
+}
@@ -0,0 +1,290 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::{Cache, RandomState};
+
+struct RawNode<K, V> {
+    key: K,
+    value: V,
+    prev: Option<NonNull<RawNode<K, V>>>,
+    next: Option<NonNull<RawNode<K, V>>>
+}
+
+// a plain lru cache whose list is linked through pointers to boxed nodes, like the `lru` crate, instead of
+// checked slots. following a link is a load rather than a bounds checked index, which is measurably faster on
+// the promote and evict paths, at the price of unsafe code. there are no ttls, weights or policies
+//
+// every pointer in `map`, `head`, `tail` and the nodes' links points to a live node owned by this cache,
+// each node is in the map and in the list exactly once, and nodes are only freed after being unlinked.
+// the tests run under miri: `cargo +nightly miri test --features raw raw::`
+pub struct RawLruCache<K: Clone + Eq + Hash, V> {
+    capacity: usize,
+    map: HashMap<K, NonNull<RawNode<K, V>>, RandomState>,
+    // least recently used
+    head: Option<NonNull<RawNode<K, V>>>,
+    tail: Option<NonNull<RawNode<K, V>>>,
+    // the cache owns its nodes, for drop check and auto traits
+    marker: PhantomData<Box<RawNode<K, V>>>
+}
+
+// the nodes are only reachable through the cache, so it can move and be shared like the keys and values
+unsafe impl<K: Clone + Eq + Hash + Send, V: Send> Send for RawLruCache<K, V> {}
+unsafe impl<K: Clone + Eq + Hash + Sync, V: Sync> Sync for RawLruCache<K, V> {}
+
+impl<K: Clone + Eq + Hash, V: Clone> RawLruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        RawLruCache { capacity, map: HashMap::default(), head: None, tail: None, marker: PhantomData }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let node = *self.map.get(key)?;
+        self.relink_back(node);
+        // SAFETY: pointers in the map are live nodes of this cache
+        Some(unsafe { node.as_ref() }.value.clone())
+    }
+
+    // reads a value without marking it as recently used
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let node = self.map.get(key)?;
+        // SAFETY: pointers in the map are live nodes of this cache
+        Some(unsafe { node.as_ref() }.value.clone())
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    // returns the entry evicted to make room, or the new one itself if the cache holds nothing
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        if self.capacity == 0 {
+            return vec![(key, value)];
+        }
+        if let Some(mut node) = self.map.get(&key).copied() {
+            // SAFETY: pointers in the map are live nodes of this cache, and no other reference to it is held
+            unsafe { node.as_mut() }.value = value;
+            self.relink_back(node);
+            return Vec::new();
+        }
+
+        let evicted = if self.map.len() >= self.capacity { self.pop_lru() } else { None };
+        let node = Box::new(RawNode { key: key.clone(), value, prev: None, next: None });
+        let node = NonNull::from(Box::leak(node));
+        self.map.insert(key, node);
+        self.link_back(node);
+        evicted.into_iter().collect()
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Eq>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let node = self.map.remove(key)?;
+        Some(self.free(node).value)
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let head = self.head?;
+        // SAFETY: head is a live node of this cache
+        self.map.remove(&unsafe { head.as_ref() }.key);
+        let node = self.free(head);
+        Some((node.key, node.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // least recently used first
+    pub fn entries(&self) -> Vec<(K, V)> {
+        let mut entries = Vec::with_capacity(self.map.len());
+        let mut current = self.head;
+        while let Some(node) = current {
+            // SAFETY: links only point to live nodes of this cache
+            let node = unsafe { node.as_ref() };
+            entries.push((node.key.clone(), node.value.clone()));
+            current = node.next;
+        }
+        entries
+    }
+
+    pub fn reset(&mut self) {
+        while self.pop_lru().is_some() {}
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> RawLruCache<K, V> {
+    // unlinks a node that is already out of the map and takes back ownership of it
+    fn free(&mut self, node: NonNull<RawNode<K, V>>) -> RawNode<K, V> {
+        self.unlink(node);
+        // SAFETY: the node came from Box::leak and is no longer reachable from the map or the list
+        *unsafe { Box::from_raw(node.as_ptr()) }
+    }
+
+    fn relink_back(&mut self, node: NonNull<RawNode<K, V>>) {
+        if self.tail != Some(node) {
+            self.unlink(node);
+            self.link_back(node);
+        }
+    }
+
+    fn unlink(&mut self, mut node: NonNull<RawNode<K, V>>) {
+        // SAFETY: the node and its neighbours are live nodes of this cache, and distinct from one another
+        unsafe {
+            let node = node.as_mut();
+            let (prev, next) = (node.prev.take(), node.next.take());
+            match prev {
+                None => self.head = next,
+                Some(mut prev) => prev.as_mut().next = next
+            }
+            match next {
+                None => self.tail = prev,
+                Some(mut next) => next.as_mut().prev = prev
+            }
+        }
+    }
+
+    // the node must not be linked in already
+    fn link_back(&mut self, mut node: NonNull<RawNode<K, V>>) {
+        // SAFETY: the node and the tail are live nodes of this cache, and distinct
+        unsafe {
+            node.as_mut().prev = self.tail;
+            node.as_mut().next = None;
+            match self.tail {
+                None => self.head = Some(node),
+                Some(mut tail) => tail.as_mut().next = Some(node)
+            }
+        }
+        self.tail = Some(node);
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> Drop for RawLruCache<K, V> {
+    fn drop(&mut self) {
+        self.map.clear();
+        let mut current = self.head.take();
+        while let Some(node) = current {
+            // SAFETY: every node is in the list once, and is freed here after its successor is read
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            current = node.next;
+        }
+        self.tail = None;
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for RawLruCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        RawLruCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        RawLruCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        RawLruCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        RawLruCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::reference::ReferenceLru;
+
+    #[test]
+    fn test_raw_lru_cache() {
+        let mut cache = RawLruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.push(3, 3), vec![(2, 2)]);
+        assert_eq!(cache.peek(&2), None);
+        cache.put(1, 10);
+        assert_eq!(cache.entries(), vec![(3, 3), (1, 10)]);
+        assert_eq!(cache.remove(&3), Some(3));
+        assert_eq!(cache.pop_lru(), Some((1, 10)));
+        assert!(cache.is_empty());
+        assert_eq!(cache.pop_lru(), None);
+    }
+
+    #[test]
+    fn test_raw_lru_cache_drops_values() {
+        let value = Rc::new(());
+        let mut cache = RawLruCache::new(2);
+        for key in 0..5 {
+            cache.put(key, Rc::clone(&value));
+        }
+        assert_eq!(Rc::strong_count(&value), 3);
+        drop(cache);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_raw_lru_cache_zero_capacity() {
+        let mut cache = RawLruCache::new(0);
+        assert_eq!(cache.push(1, 1), vec![(1, 1)]);
+        assert_eq!(cache.get(&1), None);
+        assert!(cache.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn test_raw_matches_reference(capacity in 0..6usize, ops in proptest::collection::vec((0..3u8, 0..12u8), 0..200)) {
+            let mut cache = RawLruCache::new(capacity);
+            let mut reference = ReferenceLru::new(capacity);
+            for (op, key) in ops {
+                match op {
+                    0 => prop_assert_eq!(cache.get(&key), reference.get(&key)),
+                    1 => {
+                        cache.put(key, key);
+                        reference.put(key, key);
+                    },
+                    _ => {
+                        cache.delete(&key);
+                        reference.delete(&key);
+                    }
+                }
+                prop_assert_eq!(cache.entries(), reference.entries());
+                prop_assert_eq!(cache.len(), reference.entries().len());
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use redis::{Client, Connection, FromRedisValue, RedisError, ToRedisArgs};
+
+use crate::store::Store;
+
+// keys and values go over the wire with redis' own argument encoding
+pub struct RedisStore<K, V> {
+    connection: Mutex<Connection>,
+    marker: PhantomData<fn(K) -> V>
+}
+
+impl<K, V> RedisStore<K, V> {
+    pub fn open(url: &str) -> Result<Self, RedisError> {
+        let connection = Client::open(url)?.get_connection()?;
+        Ok(RedisStore::new(connection))
+    }
+
+    pub fn new(connection: Connection) -> Self {
+        RedisStore {
+            connection: Mutex::new(connection),
+            marker: PhantomData
+        }
+    }
+}
+
+// the ttl for SET's PX. redis refuses a deadline past its millisecond clock's range, such a ttl is sent without an
+// expiry instead
+fn px(ttl: Duration) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_millis());
+    let millis = i64::try_from(ttl.as_millis().max(1)).ok()?;
+    i64::try_from(now).ok()?.checked_add(millis)?;
+    Some(millis)
+}
+
+impl<K, V> Store<K, V> for RedisStore<K, V>
+where
+    K: ToRedisArgs,
+    V: ToRedisArgs + FromRedisValue
+{
+    type Error = RedisError;
+
+    fn get(&self, key: &K) -> Result<Option<V>, RedisError> {
+        let mut connection = self.connection.lock().unwrap();
+        redis::cmd("GET").arg(key).query(&mut *connection)
+    }
+
+    fn set(&self, key: &K, value: &V, ttl: Option<Duration>) -> Result<(), RedisError> {
+        let mut connection = self.connection.lock().unwrap();
+        let mut command = redis::cmd("SET");
+        command.arg(key).arg(value);
+        if let Some(millis) = ttl.and_then(px) {
+            command.arg("PX").arg(millis);
+        }
+        command.query(&mut *connection)
+    }
+
+    fn delete(&self, key: &K) -> Result<(), RedisError> {
+        let mut connection = self.connection.lock().unwrap();
+        redis::cmd("DEL").arg(key).query(&mut *connection)
+    }
+}
@@ -0,0 +1,88 @@
+// a naive model of the cache's observable behaviour, for differential tests against the real thing
+use crate::replay::Op;
+
+pub(crate) struct ReferenceLru<K, V> {
+    capacity: usize,
+    // least recently used first
+    entries: Vec<(K, V)>
+}
+
+impl<K: Clone + PartialEq, V: Clone> ReferenceLru<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ReferenceLru { capacity, entries: Vec::new() }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<V> {
+        let at = self.entries.iter().position(|(existing, _)| existing == key)?;
+        let entry = self.entries.remove(at);
+        self.entries.push(entry.clone());
+        Some(entry.1)
+    }
+
+    pub(crate) fn put(&mut self, key: K, value: V) {
+        match self.entries.iter().position(|(existing, _)| *existing == key) {
+            Some(at) => {
+                self.entries.remove(at);
+            },
+            None if self.capacity == 0 => return,
+            None if self.entries.len() >= self.capacity => {
+                self.entries.remove(0);
+            },
+            None => ()
+        }
+        self.entries.push((key, value));
+    }
+
+    pub(crate) fn delete(&mut self, key: &K) {
+        self.entries.retain(|(existing, _)| existing != key);
+    }
+
+    pub(crate) fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let excess = self.entries.len().saturating_sub(capacity);
+        self.entries.drain(..excess);
+    }
+
+    pub(crate) fn entries(&self) -> Vec<(K, V)> {
+        self.entries.clone()
+    }
+
+    // ttls in generated ops are long enough to never expire during a test. of a configuration only the capacity
+    // is modelled
+    pub(crate) fn apply(&mut self, op: &Op<K, V>) -> Option<V> {
+        match op.clone() {
+            Op::Get(key) => return self.get(&key),
+            Op::Put(key, value) | Op::PutWithOptions(key, value, _) => self.put(key, value),
+            Op::Delete(key) => self.delete(&key),
+            Op::Resize(capacity) => self.resize(capacity),
+            Op::Reset => self.entries.clear(),
+            Op::Configure(config) => self.resize(config.capacity)
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::LruCache;
+    use crate::test_support::ops;
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_matches_reference(ops in ops(0..16u8, any::<u8>(), 200)) {
+            let mut cache = LruCache::new(0);
+            let mut reference = ReferenceLru::new(0);
+            for op in &ops {
+                let expected = reference.apply(op);
+                match op {
+                    Op::Get(key) => prop_assert_eq!(cache.get(key), expected),
+                    op => op.apply(&mut cache)
+                }
+                prop_assert_eq!(cache.entries(), reference.entries(), "after {:?}", op);
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::hash::Hash;
+
+use crate::{Cache, CacheError, CapacityPolicy, EntryOptions, LruCache, OversizedPolicy};
+use crate::elastic::Growth;
+
+// the settings a trace carries, so replay rebuilds a cache that behaves like the recorded one.
+// weighers and admission policies are code, not data, and caches using them can't be recorded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub capacity: usize,
+    pub defaults: EntryOptions,
+    pub max_weight: usize,
+    pub max_entry_weight: usize,
+    pub oversized: OversizedPolicy,
+    pub capacity_policy: CapacityPolicy,
+    pub promote_every: u64
+}
+
+impl Config {
+    pub fn of<K: Clone + Eq + Hash, V: Clone>(cache: &LruCache<K, V>) -> Self {
+        Config {
+            capacity: cache.capacity,
+            defaults: cache.defaults,
+            max_weight: cache.max_weight,
+            max_entry_weight: cache.max_entry_weight,
+            oversized: cache.oversized,
+            capacity_policy: cache.growth.as_ref().map_or(CapacityPolicy::Fixed, |growth| growth.policy()),
+            promote_every: cache.promote_every
+        }
+    }
+
+    // applies the settings in place, trimming whatever no longer fits
+    fn apply<K: Clone + Eq + Hash, V: Clone>(&self, cache: &mut LruCache<K, V>) {
+        cache.defaults = self.defaults;
+        cache.max_weight = self.max_weight;
+        cache.max_entry_weight = self.max_entry_weight;
+        cache.oversized = self.oversized;
+        cache.growth = Growth::for_policy(self.capacity_policy);
+        cache.promote_every = self.promote_every.max(1);
+        cache.resize(self.capacity);
+    }
+}
+
+// one recorded cache operation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<K, V> {
+    Get(K),
+    Put(K, V),
+    PutWithOptions(K, V, EntryOptions),
+    Delete(K),
+    Resize(usize),
+    Reset,
+    Configure(Config)
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Op<K, V> {
+    pub fn apply(&self, cache: &mut LruCache<K, V>) {
+        match self.clone() {
+            Op::Get(key) => {
+                cache.get(&key);
+            },
+            Op::Put(key, value) => {
+                cache.put(key, value);
+            },
+            Op::PutWithOptions(key, value, options) => {
+                cache.put_with_options(key, value, options);
+            },
+            Op::Delete(key) => cache.delete(&key),
+            Op::Resize(capacity) => cache.resize(capacity),
+            Op::Reset => cache.reset(),
+            Op::Configure(config) => config.apply(cache)
+        }
+    }
+}
+
+// wraps a cache and captures every operation applied through it, so a bug report can ship the trace.
+// the first op is the cache's configuration, replay rebuilds the same entries in the same order
+pub struct Recorder<K: Clone + Eq + Hash, V> {
+    cache: LruCache<K, V>,
+    ops: Vec<Op<K, V>>
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Recorder<K, V> {
+    // the cache must be empty and use neither a weigher nor an admission policy, else the trace couldn't
+    // reproduce it
+    pub fn new(cache: LruCache<K, V>) -> Result<Self, CacheError> {
+        if !cache.is_empty() || cache.weigher.is_some() || cache.admission.is_some() {
+            return Err(CacheError::Unrecordable);
+        }
+        let ops = vec![Op::Configure(Config::of(&cache))];
+        Ok(Recorder { cache, ops })
+    }
+
+    pub fn apply(&mut self, op: Op<K, V>) {
+        op.apply(&mut self.cache);
+        self.ops.push(op);
+    }
+
+    pub fn put_with_options(&mut self, key: K, value: V, options: EntryOptions) {
+        self.apply(Op::PutWithOptions(key, value, options));
+    }
+
+    pub fn resize(&mut self, capacity: usize) {
+        self.apply(Op::Resize(capacity));
+    }
+
+    pub fn cache(&self) -> &LruCache<K, V> {
+        &self.cache
+    }
+
+    pub fn ops(&self) -> &[Op<K, V>] {
+        &self.ops
+    }
+
+    pub fn into_ops(self) -> Vec<Op<K, V>> {
+        self.ops
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> for Recorder<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.ops.push(Op::Get(key.clone()));
+        self.cache.get(key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.ops.push(Op::Put(key.clone(), value.clone()));
+        self.cache.push(key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        self.apply(Op::Delete(key.clone()));
+    }
+
+    fn reset(&mut self) {
+        self.apply(Op::Reset);
+    }
+}
+
+// rebuilds a cache from a recorded trace. entries with a ttl expire relative to the replay,
+// not the original run
+pub fn replay<K: Clone + Eq + Hash, V: Clone>(ops: &[Op<K, V>]) -> LruCache<K, V> {
+    let mut cache = LruCache::new(usize::MAX);
+    for op in ops {
+        op.apply(&mut cache);
+    }
+    cache
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::TinyLfu;
+
+    #[test]
+    fn test_replay() {
+        let mut recorder = Recorder::new(LruCache::new(2)).unwrap();
+        recorder.put(1, 1);
+        recorder.put(2, 2);
+        recorder.get(&1);
+        recorder.put(3, 3);
+        recorder.delete(&4);
+
+        let replayed = replay(recorder.ops());
+        assert_eq!(replayed.entries(), recorder.cache().entries());
+        assert_eq!(replayed.capacity(), 2);
+        assert_eq!(recorder.into_ops().len(), 6);
+    }
+
+    #[test]
+    fn test_replay_keeps_configuration() {
+        let cache = LruCache::with_ttl(3, Duration::from_secs(60))
+            .max_entry_weight(1)
+            .oversized(OversizedPolicy::Reject)
+            .promote_every(2);
+        let mut recorder = Recorder::new(cache).unwrap();
+        recorder.put(1, 1);
+        recorder.put(2, 2);
+        recorder.get(&1);
+        recorder.put(3, 3);
+        recorder.put(4, 4);
+
+        let mut replayed = replay(recorder.ops());
+        assert_eq!(replayed.entries(), recorder.cache().entries());
+        assert_eq!(Config::of(&replayed), Config::of(recorder.cache()));
+        assert!(replayed.get_with_expiry(&4).unwrap().1.is_some());
+    }
+
+    #[test]
+    fn test_recorder_refuses_what_it_cant_replay() {
+        let weighed = LruCache::<u8, u8>::new(2).weigh_by(4, |_, value| *value as usize);
+        assert_eq!(Recorder::new(weighed).err(), Some(CacheError::Unrecordable));
+        let admitting = LruCache::<u8, u8>::new(2).admission(TinyLfu::new(16));
+        assert_eq!(Recorder::new(admitting).err(), Some(CacheError::Unrecordable));
+        let mut filled = LruCache::new(2);
+        filled.put(1, 1);
+        assert_eq!(Recorder::new(filled).err(), Some(CacheError::Unrecordable));
+    }
+}
@@ -0,0 +1,129 @@
+use std::hash::Hash;
+
+use crate::LruCache;
+
+// the cache's structural invariants as found by debug_report. a healthy cache has every count equal to
+// map_len and every flag false, is_consistent checks all of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugReport {
+    pub map_len: usize,
+    // nodes in the node storage
+    pub node_count: usize,
+    // nodes reached following next links from the head, and prev links from the tail
+    pub forward_len: usize,
+    pub backward_len: usize,
+    // the head has a predecessor, the tail a successor, or only one of them is set
+    pub head_tail_mismatch: bool,
+    // links to an empty slot, or next links whose target doesn't link back
+    pub broken_links: usize,
+    // a walk took more steps than there are nodes
+    pub cycle: bool,
+    // nodes no map entry points to
+    pub orphaned_nodes: usize,
+    // map entries pointing to an empty slot or to another key's node
+    pub dangling_keys: usize,
+    // the tracked weight differs from the sum of the nodes' weights
+    pub weight_mismatch: bool
+}
+
+impl DebugReport {
+    pub fn is_consistent(&self) -> bool {
+        self.node_count == self.map_len
+            && self.forward_len == self.map_len
+            && self.backward_len == self.map_len
+            && !self.head_tail_mismatch
+            && self.broken_links == 0
+            && !self.cycle
+            && self.orphaned_nodes == 0
+            && self.dangling_keys == 0
+            && !self.weight_mismatch
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
+    // walks every structure of the cache to check they agree, in O(len). meant for embedders' integration tests
+    // and debug builds, to catch corruption close to where it happened
+    pub fn debug_report(&self) -> DebugReport {
+        let mut report = DebugReport { map_len: self.map.len(), node_count: self.nodes.len(), ..DebugReport::default() };
+
+        let mut current = self.head;
+        while let Some(slot) = current {
+            if report.forward_len == report.node_count {
+                report.cycle = true;
+                break;
+            }
+            // a dangling next link was counted at its node, only a dangling head is left
+            let Some(node) = self.nodes.get(slot) else {
+                if report.forward_len == 0 {
+                    report.broken_links += 1;
+                }
+                break;
+            };
+            report.forward_len += 1;
+            match node.next.map(|next| self.nodes.get(next)) {
+                Some(None) => report.broken_links += 1,
+                Some(Some(next)) if next.prev != Some(slot) => report.broken_links += 1,
+                _ => ()
+            }
+            current = node.next;
+        }
+
+        let mut current = self.tail;
+        while let Some(slot) = current {
+            if report.backward_len == report.node_count {
+                report.cycle = true;
+                break;
+            }
+            let Some(node) = self.nodes.get(slot) else { break };
+            report.backward_len += 1;
+            current = node.prev;
+        }
+
+        let head = self.head.and_then(|slot| self.nodes.get(slot));
+        let tail = self.tail.and_then(|slot| self.nodes.get(slot));
+        report.head_tail_mismatch = head.is_some_and(|node| node.prev.is_some())
+            || tail.is_some_and(|node| node.next.is_some())
+            || self.head.is_some() != self.tail.is_some();
+
+        for (slot, node) in self.nodes.iter_with_slots() {
+            if self.map.get(&node.key) != Some(&slot) {
+                report.orphaned_nodes += 1;
+            }
+        }
+        report.dangling_keys = self.map.iter()
+            .filter(|(key, slot)| self.nodes.get(**slot).is_none_or(|node| node.key != **key))
+            .count();
+        let weight = self.nodes.iter().fold(0usize, |total, node| total.saturating_add(node.weight));
+        report.weight_mismatch = weight != self.weight;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_report() {
+        let mut lru = LruCache::new(3);
+        assert!(lru.debug_report().is_consistent());
+        for key in 0..5 {
+            lru.put(key, key);
+        }
+        lru.get(&2);
+        lru.delete(&3);
+        let report = lru.debug_report();
+        assert!(report.is_consistent(), "{:?}", report);
+        assert_eq!(report.forward_len, 2);
+
+        let head = lru.head.unwrap();
+        lru.nodes[head].next = Some(7);
+        lru.weight += 1;
+        let report = lru.debug_report();
+        assert!(!report.is_consistent());
+        assert_eq!(report.broken_links, 1);
+        assert_eq!(report.forward_len, 1);
+        assert_eq!(report.backward_len, 2);
+        assert!(report.weight_mismatch);
+    }
+}
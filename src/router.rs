@@ -0,0 +1,59 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hash, Hasher};
+
+// decides which shard of a concurrent cache owns a key
+pub trait ShardRouter<K: ?Sized>: Send + Sync {
+    fn route(&self, key: &K, shards: usize) -> usize;
+}
+
+// hash modulo shard count, with a per-process random seed
+#[derive(Clone, Default)]
+pub struct HashRouter {
+    hasher: RandomState
+}
+
+impl<K: Hash + ?Sized> ShardRouter<K> for HashRouter {
+    fn route(&self, key: &K, shards: usize) -> usize {
+        self.hasher.hash_one(key) as usize % shards
+    }
+}
+
+// jump consistent hashing: deterministic across processes built with the same
+// compiler, and only about 1/n of the keys move when a shard is added
+#[derive(Clone, Copy, Default)]
+pub struct ConsistentHashRouter;
+
+impl<K: Hash + ?Sized> ShardRouter<K> for ConsistentHashRouter {
+    fn route(&self, key: &K, shards: usize) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        jump_hash(hasher.finish(), shards)
+    }
+}
+
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+    while next < buckets as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket.max(0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consistent_router_moves_few_keys() {
+        let router = ConsistentHashRouter;
+        let moved = (0..1000u32)
+            .filter(|key| router.route(key, 10) != router.route(key, 11))
+            .count();
+        assert!(moved < 200);
+        assert!((0..1000u32).all(|key| router.route(&key, 10) < 10));
+        assert_eq!(router.route(&7u32, 10), router.route(&7u32, 10));
+    }
+}
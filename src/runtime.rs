@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+// the few things the async cache needs from an executor
+pub trait Runtime: Clone + Send + Sync + 'static {
+    fn spawn(&self, task: BoxFuture<()>);
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()>;
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Clone)]
+pub struct TokioRuntime {
+    handle: tokio::runtime::Handle
+}
+
+#[cfg(feature = "tokio")]
+impl TokioRuntime {
+    // panics when called outside of a tokio runtime
+    pub fn current() -> Self {
+        TokioRuntime { handle: tokio::runtime::Handle::current() }
+    }
+
+    pub fn new(handle: tokio::runtime::Handle) -> Self {
+        TokioRuntime { handle }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Runtime for TokioRuntime {
+    fn spawn(&self, task: BoxFuture<()>) {
+        self.handle.spawn(task);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn spawn(&self, task: BoxFuture<()>) {
+        async_std::task::spawn(task);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+#[cfg(feature = "smol")]
+#[derive(Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl Runtime for SmolRuntime {
+    fn spawn(&self, task: BoxFuture<()>) {
+        smol::spawn(task).detach();
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::{AsyncLoadingCache, LruCache};
+
+    #[test]
+    fn test_tokio_runtime_drives_async_cache() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let cache = AsyncLoadingCache::new(LruCache::new(2), TokioRuntime::current(), |key: i32| async move {
+                Some(key + 1)
+            });
+            TokioRuntime::current().sleep(Duration::from_millis(1)).await;
+            assert_eq!(cache.get(&1).await, Some(2));
+            assert_eq!(cache.len(), 1);
+        });
+    }
+}
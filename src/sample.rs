@@ -0,0 +1,51 @@
+use std::hash::Hash;
+use std::time::Instant;
+
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::LruCache;
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
+    // a uniform random sample of up to n live entries, in no particular order. only the sampled entries are
+    // cloned and the recency list isn't walked, so it is cheap enough to run on a big cache for monitoring
+    pub fn sample<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Vec<(K, V)> {
+        let now = Instant::now();
+        self.nodes.iter()
+            .filter(|node| !node.is_expired(now))
+            .choose_multiple(rng, n)
+            .into_iter()
+            .map(|node| (node.key.clone(), node.value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_sample_is_uniform() {
+        let mut cache = LruCache::new(100);
+        for i in 0..100 {
+            cache.put(i, i);
+        }
+        let mut rng = StdRng::seed_from_u64(7);
+        let sample = cache.sample(10, &mut rng);
+        assert_eq!(sample.len(), 10);
+        assert!(sample.iter().all(|(key, value)| key == value));
+        assert_eq!(cache.sample(200, &mut rng).len(), 100);
+
+        // every entry turns up about as often as any other
+        let mut seen = [0; 100];
+        for _ in 0..1000 {
+            for (key, _) in cache.sample(10, &mut rng) {
+                seen[key] += 1;
+            }
+        }
+        assert!(seen.iter().all(|&count| (50..150).contains(&count)), "{:?}", seen);
+    }
+}
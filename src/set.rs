@@ -0,0 +1,97 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::{InsertOutcome, LruCache};
+
+// a bounded set of recently seen keys, for dedup windows and replay protection. the values are (),
+// which takes no space in the nodes, so an entry costs no more than its key and the list links
+pub struct LruSet<K: Clone + Eq + Hash> {
+    cache: LruCache<K, ()>
+}
+
+impl<K: Clone + Eq + Hash> LruSet<K> {
+    pub fn new(capacity: usize) -> Self {
+        LruSet { cache: LruCache::new(capacity) }
+    }
+
+    // keys are forgotten `ttl` after their last insert, however often they were checked in between
+    pub fn with_ttl(capacity: usize, ttl: Duration) -> Self {
+        LruSet { cache: LruCache::with_ttl(capacity, ttl) }
+    }
+
+    // adds or promotes the key, true if it wasn't in the set. a duplicate restarts its ttl
+    pub fn insert(&mut self, key: K) -> bool {
+        self.cache.put(key, ()) == InsertOutcome::Inserted
+    }
+
+    // doesn't change the key's recency
+    pub fn contains<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.cache.contains_key(key)
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Eq>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.cache.remove(key).is_some()
+    }
+
+    pub fn pop_lru(&mut self) -> Option<K> {
+        self.cache.pop_lru().map(|(key, _)| key)
+    }
+
+    // least recently inserted first
+    pub fn keys(&self) -> Vec<K> {
+        self.cache.entries().into_iter().map(|(key, _)| key).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_set() {
+        let mut seen = LruSet::new(2);
+        assert!(seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("c"));
+        assert!(!seen.contains(&"b"));
+        assert_eq!(seen.keys(), vec!["a", "c"]);
+        assert!(seen.remove(&"a"));
+        assert!(!seen.remove(&"a"));
+        assert_eq!(seen.pop_lru(), Some("c"));
+        assert!(seen.is_empty());
+    }
+
+    #[test]
+    fn test_lru_set_ttl() {
+        let mut nonces = LruSet::with_ttl(10, Duration::from_millis(20));
+        assert!(nonces.insert(1));
+        assert!(!nonces.insert(1));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!nonces.contains(&1));
+        assert!(nonces.insert(1));
+    }
+}
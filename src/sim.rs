@@ -0,0 +1,219 @@
+// replays access traces against a policy to size caches before deploying them
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Cache, ClockProCache, LirsCache, LruCache, LruKCache, TinyLfu};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    // a read that fills the cache on a miss, what a bare key line means
+    Read(String),
+    Get(String),
+    Put(String),
+    Delete(String)
+}
+
+// one key per line, or `op,key` with op get, put or delete. blank lines and # comments are skipped
+pub fn parse_trace(input: &str) -> Result<Vec<Access>, String> {
+    let mut trace = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let access = match line.split_once(',') {
+            None => Access::Read(line.to_string()),
+            Some((op, key)) => {
+                let key = key.trim().to_string();
+                match op.trim().to_ascii_lowercase().as_str() {
+                    "get" => Access::Get(key),
+                    "put" | "set" => Access::Put(key),
+                    "delete" | "del" => Access::Delete(key),
+                    op => return Err(format!("line {}: unknown op {:?}", number + 1, op))
+                }
+            }
+        };
+        trace.push(access);
+    }
+    Ok(trace)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    Lru,
+    // LRU-2
+    LruK,
+    TinyLfu,
+    ClockPro,
+    Lirs
+}
+
+impl Policy {
+    pub const ALL: [Policy; 5] = [Policy::Lru, Policy::LruK, Policy::TinyLfu, Policy::ClockPro, Policy::Lirs];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Policy::Lru => "lru",
+            Policy::LruK => "lru-2",
+            Policy::TinyLfu => "tinylfu",
+            Policy::ClockPro => "clock-pro",
+            Policy::Lirs => "lirs"
+        }
+    }
+
+    fn cache(self, capacity: usize) -> Box<dyn Cache<String, ()>> {
+        match self {
+            Policy::Lru => Box::new(LruCache::new(capacity)),
+            Policy::LruK => Box::new(LruKCache::new(capacity)),
+            Policy::TinyLfu => Box::new(LruCache::new(capacity).admission(TinyLfu::new(capacity).doorkeeper(capacity))),
+            Policy::ClockPro => Box::new(ClockProCache::new(capacity)),
+            Policy::Lirs => Box::new(LirsCache::new(capacity))
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Policy::ALL.into_iter()
+            .find(|policy| policy.name() == name)
+            .ok_or_else(|| format!("unknown policy {:?}, expected one of lru, lru-2, tinylfu, clock-pro, lirs", name))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub policy: Policy,
+    pub capacity: usize,
+    pub lookups: u64,
+    pub hits: u64,
+    pub evictions: u64,
+    pub distinct_keys: usize,
+    // keys looked up exactly once
+    pub one_hit_wonders: usize,
+    // fraction of lookups going to the most popular 10% of keys
+    pub top_decile_share: f64
+}
+
+impl Report {
+    pub fn misses(&self) -> u64 {
+        self.lookups - self.hits
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups as f64
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "policy           {}", self.policy.name())?;
+        writeln!(f, "capacity         {}", self.capacity)?;
+        writeln!(f, "lookups          {}", self.lookups)?;
+        writeln!(f, "hits             {}", self.hits)?;
+        writeln!(f, "misses           {}", self.misses())?;
+        writeln!(f, "hit rate         {:.2}%", self.hit_rate() * 100.0)?;
+        writeln!(f, "evictions        {}", self.evictions)?;
+        writeln!(f, "distinct keys    {}", self.distinct_keys)?;
+        writeln!(f, "one-hit wonders  {}", self.one_hit_wonders)?;
+        write!(f, "top 10% share    {:.2}%", self.top_decile_share * 100.0)
+    }
+}
+
+pub fn simulate(policy: Policy, capacity: usize, trace: &[Access]) -> Report {
+    let mut cache = policy.cache(capacity);
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    let (mut lookups, mut hits, mut evictions) = (0, 0, 0);
+
+    for access in trace {
+        match access {
+            Access::Read(key) | Access::Get(key) => {
+                lookups += 1;
+                *counts.entry(key).or_default() += 1;
+                if cache.get(key).is_some() {
+                    hits += 1;
+                } else if let Access::Read(_) = access {
+                    evictions += cache.push(key.clone(), ()).len() as u64;
+                }
+            },
+            Access::Put(key) => evictions += cache.push(key.clone(), ()).len() as u64,
+            Access::Delete(key) => cache.delete(key)
+        }
+    }
+
+    let mut popularity: Vec<u64> = counts.values().copied().collect();
+    popularity.sort_unstable_by(|a, b| b.cmp(a));
+    let top_decile: u64 = popularity.iter().take(popularity.len().div_ceil(10)).sum();
+    Report {
+        policy,
+        capacity,
+        lookups,
+        hits,
+        evictions,
+        distinct_keys: counts.len(),
+        one_hit_wonders: popularity.iter().filter(|&&count| count == 1).count(),
+        top_decile_share: if lookups == 0 { 0.0 } else { top_decile as f64 / lookups as f64 }
+    }
+}
+
+// every policy at every capacity over the same trace
+pub fn compare(policies: &[Policy], capacities: &[usize], trace: &[Access]) -> Vec<Report> {
+    policies.iter()
+        .flat_map(|&policy| capacities.iter().map(move |&capacity| simulate(policy, capacity, trace)))
+        .collect()
+}
+
+// one row per report, best hit rate first
+pub fn comparison_table(reports: &[Report]) -> String {
+    let mut reports: Vec<&Report> = reports.iter().collect();
+    reports.sort_by(|a, b| b.hit_rate().total_cmp(&a.hit_rate()));
+
+    let mut table = format!("{:<10} {:>10} {:>9} {:>10} {:>10} {:>10}\n", "policy", "capacity", "hit rate", "hits", "misses", "evictions");
+    for report in reports {
+        table.push_str(&format!("{:<10} {:>10} {:>8.2}% {:>10} {:>10} {:>10}\n",
+            report.policy.name(), report.capacity, report.hit_rate() * 100.0, report.hits, report.misses(), report.evictions));
+    }
+    table
+}
+
+pub fn to_json(reports: &[Report]) -> String {
+    let rows: Vec<String> = reports.iter().map(|report| format!(
+        "{{\"policy\":\"{}\",\"capacity\":{},\"lookups\":{},\"hits\":{},\"misses\":{},\"hit_rate\":{},\"evictions\":{},\"distinct_keys\":{},\"one_hit_wonders\":{},\"top_decile_share\":{}}}",
+        report.policy.name(), report.capacity, report.lookups, report.hits, report.misses(), report.hit_rate(),
+        report.evictions, report.distinct_keys, report.one_hit_wonders, report.top_decile_share
+    )).collect();
+    format!("[{}]", rows.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulate_trace() {
+        let trace = parse_trace("a\nb\na\n# comment\nget,c\nput,c\nGET,c\ndelete,a\na\n").unwrap();
+        assert_eq!(trace[3], Access::Get("c".to_string()));
+        let report = simulate(Policy::Lru, 2, &trace);
+        assert_eq!((report.lookups, report.hits, report.evictions), (6, 2, 1));
+        assert_eq!(report.distinct_keys, 3);
+        assert_eq!(report.one_hit_wonders, 1);
+        assert!(parse_trace("fetch,a").is_err());
+        assert_eq!("clock-pro".parse(), Ok(Policy::ClockPro));
+    }
+
+    #[test]
+    fn test_compare_policies() {
+        let trace = parse_trace(&(0..20).flat_map(|_| 0..12).map(|key| format!("{}\n", key)).collect::<String>()).unwrap();
+        let reports = compare(&Policy::ALL, &[10, 20], &trace);
+        assert_eq!(reports.len(), 10);
+        let table = comparison_table(&reports);
+        assert_eq!(table.lines().count(), 11);
+        assert!(table.lines().nth(1).unwrap().ends_with(" 0"), "{}", table);
+        assert!(to_json(&reports).starts_with("[{\"policy\":\"lru\",\"capacity\":10,"));
+    }
+}
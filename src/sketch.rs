@@ -0,0 +1,95 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+const ROWS: usize = 4;
+const SEEDS: [u64; ROWS] = [0x9e37_79b9_7f4a_7c15, 0xc2b2_ae3d_27d4_eb4f, 0x1656_67b1_9e37_79f9, 0x85eb_ca77_c2b2_ae63];
+// counters saturate here, recent popularity matters more than exact counts
+const MAX_COUNT: u8 = 15;
+
+// a count-min sketch estimating how often keys were seen, in fixed memory.
+// every counter is halved once 10 samples per slot were recorded so old popularity fades
+pub struct FrequencySketch {
+    table: Vec<u8>,
+    mask: usize,
+    samples: usize,
+    sample_size: usize,
+    hasher: RandomState
+}
+
+impl FrequencySketch {
+    // sized for roughly `capacity` distinct hot keys
+    pub fn new(capacity: usize) -> Self {
+        let width = capacity.max(16).next_power_of_two();
+        FrequencySketch {
+            table: vec![0; width * ROWS],
+            mask: width - 1,
+            samples: 0,
+            sample_size: width * 10,
+            hasher: RandomState::new()
+        }
+    }
+
+    pub fn record<K: Hash + ?Sized>(&mut self, key: &K) {
+        let hash = self.hasher.hash_one(key);
+        for row in 0..ROWS {
+            let slot = self.slot(hash, row);
+            if self.table[slot] < MAX_COUNT {
+                self.table[slot] += 1;
+            }
+        }
+
+        self.samples += 1;
+        if self.samples == self.sample_size {
+            self.halve();
+        }
+    }
+
+    // never below the true count since the last halving, capped at 15
+    pub fn estimate<K: Hash + ?Sized>(&self, key: &K) -> u8 {
+        let hash = self.hasher.hash_one(key);
+        (0..ROWS).map(|row| self.table[self.slot(hash, row)]).min().unwrap_or(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill(0);
+        self.samples = 0;
+    }
+
+    pub(crate) fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn halve(&mut self) {
+        for count in &mut self.table {
+            *count /= 2;
+        }
+        self.samples /= 2;
+    }
+
+    fn slot(&self, hash: u64, row: usize) -> usize {
+        let mixed = hash.wrapping_mul(SEEDS[row]);
+        row * (self.mask + 1) + ((mixed >> 32) as usize & self.mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency_sketch() {
+        let mut sketch = FrequencySketch::new(64);
+        for _ in 0..8 {
+            sketch.record("hot");
+        }
+        sketch.record("warm");
+        assert!(sketch.estimate("hot") >= 8);
+        assert!(sketch.estimate("hot") > sketch.estimate("warm"));
+
+        // the 640th sample halves every counter
+        for i in 0..631 {
+            sketch.record(&i);
+        }
+        assert!(sketch.estimate("hot") < 8);
+    }
+}
@@ -0,0 +1,102 @@
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::store::Store;
+
+// values are stored behind an 8 byte expiry (unix millis, 0 for none),
+// expired values read as missing and are removed lazily
+pub struct SledStore<K, V> {
+    tree: sled::Tree,
+    marker: PhantomData<fn(K) -> V>
+}
+
+impl<K, V> SledStore<K, V> {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+        Ok(SledStore::new(db.open_tree("lru")?))
+    }
+
+    pub fn new(tree: sled::Tree) -> Self {
+        SledStore {
+            tree,
+            marker: PhantomData
+        }
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.tree.flush().map(|_| ())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+}
+
+impl<K, V> Store<K, V> for SledStore<K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]> + From<Vec<u8>>
+{
+    type Error = sled::Error;
+
+    fn get(&self, key: &K) -> sled::Result<Option<V>> {
+        let stored = match self.tree.get(key)? {
+            None => return Ok(None),
+            Some(stored) => stored
+        };
+
+        // every value is written behind an 8 byte expiry, anything shorter wasn't written by this store
+        let Some((expiry, value)) = stored.split_first_chunk::<8>() else { return Ok(None) };
+        let expiry = u64::from_be_bytes(*expiry);
+        if expiry != 0 && expiry <= unix_millis(SystemTime::now()) {
+            self.tree.remove(key)?;
+            return Ok(None);
+        }
+        Ok(Some(V::from(value.to_vec())))
+    }
+
+    fn set(&self, key: &K, value: &V, ttl: Option<Duration>) -> sled::Result<()> {
+        // 0 stores the value without an expiry, as does a ttl reaching past what SystemTime can represent
+        let deadline = ttl.map(|ttl| SystemTime::now().checked_add(ttl));
+        let expiry = deadline.flatten().map_or(0, |deadline| unix_millis(deadline).max(1));
+        let mut stored = Vec::with_capacity(8 + value.as_ref().len());
+        stored.extend_from_slice(&expiry.to_be_bytes());
+        stored.extend_from_slice(value.as_ref());
+        self.tree.insert(key, stored)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &K) -> sled::Result<()> {
+        self.tree.remove(key)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cache, LruCache, StoreCache};
+
+    #[test]
+    fn test_sled_store_overflow_and_ttl() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let store: SledStore<String, Vec<u8>> = SledStore::new(db.open_tree("test").unwrap());
+        store.set(&"expired".to_string(), &vec![1], Some(Duration::ZERO)).unwrap();
+        assert_eq!(store.get(&"expired".to_string()).unwrap(), None);
+
+        let mut cache = LruCache::new(1).chain(StoreCache::new(store));
+        cache.put("a".to_string(), vec![1, 2]);
+        cache.put("b".to_string(), vec![3]);
+        assert_eq!(cache.second().store().get(&"a".to_string()).unwrap(), Some(vec![1, 2]));
+        assert_eq!(cache.get(&"a".to_string()), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_sled_store_skips_foreign_values() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let tree = db.open_tree("test").unwrap();
+        tree.insert("short", vec![1, 2, 3]).unwrap();
+        let store: SledStore<String, Vec<u8>> = SledStore::new(tree);
+        assert_eq!(store.get(&"short".to_string()).unwrap(), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::borrow::Borrow;
+use std::collections::BTreeSet;
+use std::hash::Hash;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Cache, LruCache};
+
+// an lru cache with a sorted index over its keys, for range and prefix queries. build it with `new`, or from a
+// configured LruCache with `into_sorted` to keep its ttl, weigher and policies
+pub struct SortedLruCache<K: Clone + Ord + Hash, V> {
+    cache: LruCache<K, V>,
+    index: BTreeSet<K>
+}
+
+impl<K: Clone + Ord + Hash, V: Clone> SortedLruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        SortedLruCache {
+            cache: LruCache::new(capacity),
+            index: BTreeSet::new()
+        }
+    }
+
+    pub fn get<Q: ?Sized + Hash + Ord + ToOwned<Owned = K>>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        let value = self.cache.get(key);
+        if value.is_none() {
+            // the lookup drops an expired entry
+            self.unindex(key);
+        }
+        value
+    }
+
+    // reads a value without changing its recency
+    pub fn peek<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.cache.peek(key)
+    }
+
+    pub fn contains_key<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>
+    {
+        self.cache.contains_key(key)
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.push(key, value);
+    }
+
+    pub fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.index.insert(key.clone());
+        let evicted = self.cache.push(key, value);
+        for (evicted_key, _) in &evicted {
+            // a rejected value is handed back while the key's previous entry stays
+            self.unindex(evicted_key);
+        }
+        evicted
+    }
+
+    pub fn delete<Q: ?Sized + Hash + Ord>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        self.remove(key);
+    }
+
+    pub fn remove<Q: ?Sized + Hash + Ord>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>
+    {
+        self.index.remove(key);
+        self.cache.remove(key)
+    }
+
+    // entries in key order, without touching their recency
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Vec<(K, V)> {
+        self.index.range(range)
+            .filter_map(|key| self.cache.peek(key).map(|value| (key.clone(), value)))
+            .collect()
+    }
+
+    // the smallest and largest keys, expired entries the index still holds are skipped
+    pub fn first_key(&self) -> Option<&K> {
+        self.index.iter().find(|key| self.cache.contains_key(key))
+    }
+
+    pub fn last_key(&self) -> Option<&K> {
+        self.index.iter().rev().find(|key| self.cache.contains_key(key))
+    }
+
+    pub fn invalidate_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let keys: Vec<K> = self.index.range(range).cloned().collect();
+        self.invalidate_keys(keys)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.reset();
+        self.index.clear();
+    }
+
+    pub fn purge_expired(&mut self) -> usize {
+        let purged = self.cache.purge_expired();
+        if purged > 0 {
+            let cache = &self.cache;
+            self.index.retain(|key| cache.node(key).is_some());
+        }
+        purged
+    }
+
+    // drops the key from the index unless the cache still holds it
+    fn unindex<Q: ?Sized + Hash + Ord>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>
+    {
+        if self.cache.node(key).is_none() {
+            self.index.remove(key);
+        }
+    }
+
+    // the index can still hold keys whose entries expired, those are dropped but not counted
+    fn invalidate_keys(&mut self, keys: Vec<K>) -> usize {
+        for key in &keys {
+            self.index.remove(key);
+        }
+        self.cache.invalidate_many(keys)
+    }
+}
+
+impl<K: Clone + Ord + Hash + Borrow<str>, V: Clone> SortedLruCache<K, V> {
+    // entries whose key starts with `prefix` in key order, without touching their recency
+    pub fn prefix(&self, prefix: &str) -> Vec<(K, V)> {
+        self.prefixed_keys(prefix)
+            .filter_map(|key| self.cache.peek::<K>(key).map(|value| (key.clone(), value)))
+            .collect()
+    }
+
+    pub fn invalidate_prefix(&mut self, prefix: &str) -> usize {
+        let keys: Vec<K> = self.prefixed_keys(prefix).cloned().collect();
+        self.invalidate_keys(keys)
+    }
+
+    fn prefixed_keys<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a K> + 'a {
+        self.index.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |key| (*key).borrow().starts_with(prefix))
+    }
+}
+
+impl<K: Clone + Ord + Hash, V: Clone> LruCache<K, V> {
+    // switches to a sorted index over the keys, keeping the entries and everything configured on the cache
+    pub fn into_sorted(self) -> SortedLruCache<K, V> {
+        let index = self.entries().into_iter().map(|(key, _)| key).collect();
+        SortedLruCache { cache: self, index }
+    }
+}
+
+impl<K: Clone + Ord + Hash, V: Clone> Cache<K, V> for SortedLruCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        SortedLruCache::get(self, key)
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        SortedLruCache::push(self, key, value)
+    }
+
+    fn delete(&mut self, key: &K) {
+        SortedLruCache::delete(self, key)
+    }
+
+    fn reset(&mut self) {
+        SortedLruCache::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::OversizedPolicy;
+
+    #[test]
+    fn test_sorted_invalidate_prefix() {
+        let mut cache = SortedLruCache::new(10);
+        cache.put("user:42:name".to_string(), 1);
+        cache.put("user:42:email".to_string(), 2);
+        cache.put("user:420:name".to_string(), 3);
+        cache.put("user:7:name".to_string(), 4);
+        assert_eq!(cache.invalidate_prefix("user:42:"), 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"user:420:name".to_string()), Some(3));
+    }
+
+    #[test]
+    fn test_sorted_range_after_eviction() {
+        let mut cache = SortedLruCache::new(3);
+        cache.put(5, 5);
+        cache.put(1, 1);
+        cache.put(3, 3);
+        cache.put(4, 4);
+        assert_eq!(cache.range(..), vec![(1, 1), (3, 3), (4, 4)]);
+        assert_eq!(cache.range(2..4), vec![(3, 3)]);
+        assert_eq!(cache.invalidate_range(3..), 2);
+        assert_eq!(cache.range(..), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_sorted_first_last_and_prefix() {
+        let mut lru = LruCache::new(3);
+        lru.put("b:2".to_string(), 2);
+        lru.put("a:1".to_string(), 1);
+        let mut cache = lru.into_sorted();
+        assert_eq!(cache.first_key().map(String::as_str), Some("a:1"));
+        cache.put("b:3".to_string(), 3);
+        assert_eq!(cache.prefix("b:"), vec![("b:2".to_string(), 2), ("b:3".to_string(), 3)]);
+        // evicts b:2, the least recently used
+        cache.put("c:4".to_string(), 4);
+        assert_eq!(cache.prefix("b:"), vec![("b:3".to_string(), 3)]);
+        assert_eq!(cache.last_key().map(String::as_str), Some("c:4"));
+        assert!(cache.prefix("d:").is_empty());
+    }
+
+    #[test]
+    fn test_sorted_index_pruned_on_expiry() {
+        let mut cache = LruCache::with_ttl(10, Duration::from_millis(10)).into_sorted();
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        sleep(Duration::from_millis(20));
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.index.len(), 2);
+        assert_eq!(cache.purge_expired(), 2);
+        assert!(cache.index.is_empty());
+    }
+
+    #[test]
+    fn test_sorted_index_keeps_rejected_keys() {
+        let lru = LruCache::new(10).weigh_by(10, |_, value: &usize| *value).oversized(OversizedPolicy::Reject);
+        let mut cache = lru.into_sorted();
+        cache.put(1, 1);
+        assert_eq!(cache.push(1, 20), vec![(1, 20)]);
+        assert_eq!(cache.first_key(), Some(&1));
+        assert_eq!(cache.push(2, 20), vec![(2, 20)]);
+        assert_eq!(cache.index.len(), 1);
+    }
+
+    #[test]
+    fn test_sorted_invalidate_counts_live_entries() {
+        let mut cache = LruCache::with_ttl(10, Duration::from_millis(10)).into_sorted();
+        cache.put("a:1".to_string(), 1);
+        sleep(Duration::from_millis(20));
+        cache.put("a:2".to_string(), 2);
+        assert_eq!(cache.invalidate_prefix("a:"), 1);
+        assert!(cache.index.is_empty());
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+const INITIAL_SIZE: usize = 1 << 16;
+
+// a growable memory mapped file handing out regions first fit, freed regions are reused
+pub(crate) struct SpillFile {
+    file: File,
+    map: MmapMut,
+    end: usize,
+    // sorted by offset, adjacent regions are merged and a region reaching `end` is given back to the tail
+    free: Vec<(usize, usize)>
+}
+
+impl SpillFile {
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len(INITIAL_SIZE as u64)?;
+        // the file is private to this cache and only accessed through the map
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(SpillFile {
+            file,
+            map,
+            end: 0,
+            free: Vec::new()
+        })
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let offset = match self.free.iter().position(|(_, len)| *len >= bytes.len()) {
+            Some(index) => {
+                let (offset, len) = self.free[index];
+                if len > bytes.len() {
+                    self.free[index] = (offset + bytes.len(), len - bytes.len());
+                } else {
+                    self.free.remove(index);
+                }
+                offset
+            },
+            None => {
+                self.reserve(self.end + bytes.len())?;
+                let offset = self.end;
+                self.end += bytes.len();
+                offset
+            }
+        };
+
+        self.map[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(offset)
+    }
+
+    pub(crate) fn read(&self, offset: usize, len: usize) -> &[u8] {
+        &self.map[offset..offset + len]
+    }
+
+    pub(crate) fn free(&mut self, mut offset: usize, mut len: usize) {
+        if len == 0 {
+            return;
+        }
+        let mut index = self.free.partition_point(|(free, _)| *free < offset);
+        if let Some(&(next, next_len)) = self.free.get(index) {
+            if offset + len == next {
+                len += next_len;
+                self.free.remove(index);
+            }
+        }
+        if let Some(&(previous, previous_len)) = index.checked_sub(1).and_then(|previous| self.free.get(previous)) {
+            if previous + previous_len == offset {
+                index -= 1;
+                offset = previous;
+                len += previous_len;
+                self.free.remove(index);
+            }
+        }
+
+        if offset + len == self.end {
+            self.end = offset;
+        } else {
+            self.free.insert(index, (offset, len));
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.end = 0;
+        self.free.clear();
+    }
+
+    fn reserve(&mut self, size: usize) -> io::Result<()> {
+        if size <= self.map.len() {
+            return Ok(());
+        }
+        let size = size.next_power_of_two();
+        self.file.set_len(size as u64)?;
+        self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_file_coalesces_free_regions() {
+        let path = std::env::temp_dir().join(format!("lru-spill-file-{}", std::process::id()));
+        let mut file = SpillFile::create(&path).unwrap();
+        let a = file.write(&[1; 10]).unwrap();
+        let b = file.write(&[2; 10]).unwrap();
+        let c = file.write(&[3; 10]).unwrap();
+        let d = file.write(&[4; 10]).unwrap();
+        file.free(a, 10);
+        file.free(c, 10);
+        file.free(b, 10);
+        assert_eq!(file.free, vec![(0, 30)]);
+        assert_eq!(file.write(&[5; 25]).unwrap(), 0);
+        assert_eq!(file.read(d, 10), &[4; 10]);
+
+        // takes the rest of the merged region with it
+        file.free(d, 10);
+        assert_eq!(file.end, 25);
+        file.free(0, 25);
+        assert_eq!(file.end, 0);
+        assert!(file.free.is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
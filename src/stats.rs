@@ -0,0 +1,184 @@
+use std::time::Instant;
+
+use crate::audit::{AuditOp, AuditOutcome};
+
+// counters since the cache was created plus its current size. field names are stable,
+// they are the json keys and csv columns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub updates: u64,
+    // turned away by the admission or oversized policy
+    pub rejections: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub len: usize,
+    pub capacity: usize,
+    pub weight: usize,
+    pub max_weight: usize
+}
+
+impl CacheStats {
+    // the columns of to_csv_row
+    pub const CSV_HEADER: &'static str = "hits,misses,inserts,updates,rejections,evictions,expirations,len,capacity,weight,max_weight";
+
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups() == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups() as f64
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        format!("{},{},{},{},{},{},{},{},{},{},{}", self.hits, self.misses, self.inserts, self.updates, self.rejections,
+            self.evictions, self.expirations, self.len, self.capacity, self.weight, self.max_weight)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("stats are plain numbers")
+    }
+
+    pub(crate) fn record(&mut self, op: AuditOp, outcome: AuditOutcome) {
+        let counter = match (op, outcome) {
+            (AuditOp::Get, AuditOutcome::Hit) => &mut self.hits,
+            (AuditOp::Get, AuditOutcome::Miss) => &mut self.misses,
+            (AuditOp::Put, AuditOutcome::Inserted) => &mut self.inserts,
+            (AuditOp::Put, AuditOutcome::Updated) => &mut self.updates,
+            (AuditOp::Put, AuditOutcome::Rejected) => &mut self.rejections,
+            (AuditOp::Evict, _) => &mut self.evictions,
+            (AuditOp::Expire, _) => &mut self.expirations,
+            _ => return
+        };
+        *counter += 1;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WindowStats {
+    pub hits: u64,
+    pub misses: u64
+}
+
+impl WindowStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups() == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups() as f64
+    }
+}
+
+// lookups over the trailing windows, each accurate to one 10 second bucket
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WindowedStats {
+    pub last_1m: WindowStats,
+    pub last_5m: WindowStats,
+    pub last_15m: WindowStats
+}
+
+const BUCKET_SECS: u64 = 10;
+const BUCKETS: usize = 90;
+
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    index: u64,
+    counts: WindowStats
+}
+
+// a ring of 10 second buckets covering the last 15 minutes, slots are reused once their bucket is too old
+pub(crate) struct Windows {
+    started: Instant,
+    buckets: [Bucket; BUCKETS]
+}
+
+impl Windows {
+    pub(crate) fn new() -> Self {
+        Windows { started: Instant::now(), buckets: [Bucket::default(); BUCKETS] }
+    }
+
+    pub(crate) fn record(&mut self, op: AuditOp, outcome: AuditOutcome) {
+        match (op, outcome) {
+            (AuditOp::Get, AuditOutcome::Hit) => self.record_at(true, Instant::now()),
+            (AuditOp::Get, AuditOutcome::Miss) => self.record_at(false, Instant::now()),
+            _ => ()
+        }
+    }
+
+    fn record_at(&mut self, hit: bool, now: Instant) {
+        let index = self.index(now);
+        let bucket = &mut self.buckets[index as usize % BUCKETS];
+        if bucket.index != index {
+            *bucket = Bucket { index, counts: WindowStats::default() };
+        }
+        if hit {
+            bucket.counts.hits += 1;
+        } else {
+            bucket.counts.misses += 1;
+        }
+    }
+
+    pub(crate) fn stats(&self) -> WindowedStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn stats_at(&self, now: Instant) -> WindowedStats {
+        let current = self.index(now);
+        let window = |minutes: u64| {
+            let span = minutes * 60 / BUCKET_SECS;
+            self.buckets.iter()
+                .filter(|bucket| bucket.index <= current && current - bucket.index < span)
+                .fold(WindowStats::default(), |total, bucket| WindowStats {
+                    hits: total.hits + bucket.counts.hits,
+                    misses: total.misses + bucket.counts.misses
+                })
+        };
+        WindowedStats { last_1m: window(1), last_5m: window(5), last_15m: window(15) }
+    }
+
+    fn index(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs() / BUCKET_SECS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_windows_age_out() {
+        let mut windows = Windows::new();
+        let started = windows.started;
+        let at = |secs| started + Duration::from_secs(secs);
+        let (start, four_minutes, ten_minutes) = (at(0), at(240), at(600));
+        windows.record_at(true, start);
+        windows.record_at(false, start);
+        windows.record_at(true, four_minutes);
+        windows.record_at(true, ten_minutes);
+
+        let stats = windows.stats_at(ten_minutes);
+        assert_eq!(stats.last_1m, WindowStats { hits: 1, misses: 0 });
+        assert_eq!(stats.last_5m.lookups(), 1);
+        assert_eq!(stats.last_15m, WindowStats { hits: 3, misses: 1 });
+        assert_eq!(stats.last_15m.hit_rate(), 0.75);
+        assert_eq!(windows.stats_at(at(3600)).last_15m.lookups(), 0);
+
+        windows.record_at(false, at(900 + 5));
+        assert_eq!(windows.stats_at(at(905)).last_15m, WindowStats { hits: 2, misses: 1 });
+    }
+}
@@ -0,0 +1,106 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::Cache;
+
+// a fallible backing store, e.g. a remote server or a disk,
+// that a cache can sit in front of
+pub trait Store<K, V> {
+    type Error;
+
+    fn get(&self, key: &K) -> Result<Option<V>, Self::Error>;
+
+    fn set(&self, key: &K, value: &V, ttl: Option<Duration>) -> Result<(), Self::Error>;
+
+    fn delete(&self, key: &K) -> Result<(), Self::Error>;
+}
+
+// adapts a store to the `Cache` trait so it can be chained behind an in memory cache.
+// store errors count as misses on reads and are dropped on writes
+pub struct StoreCache<S, K, V> {
+    store: S,
+    ttl: Option<Duration>,
+    marker: PhantomData<fn(K) -> V>
+}
+
+impl<S, K, V> StoreCache<S, K, V> {
+    pub fn new(store: S) -> Self {
+        StoreCache {
+            store,
+            ttl: None,
+            marker: PhantomData
+        }
+    }
+
+    // passed through to the store on every write
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: Store<K, V>, K, V> Cache<K, V> for StoreCache<S, K, V> {
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.store.get(key).ok().flatten()
+    }
+
+    fn push(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let _ = self.store.set(&key, &value, self.ttl);
+        Vec::new()
+    }
+
+    fn delete(&mut self, key: &K) {
+        let _ = self.store.delete(key);
+    }
+
+    // stores are shared, so resetting the cache in front of one leaves it alone
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LruCache;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MapStore {
+        map: RefCell<HashMap<i32, (i32, Option<Duration>)>>
+    }
+
+    impl Store<i32, i32> for MapStore {
+        type Error = ();
+
+        fn get(&self, key: &i32) -> Result<Option<i32>, ()> {
+            Ok(self.map.borrow().get(key).map(|(value, _)| *value))
+        }
+
+        fn set(&self, key: &i32, value: &i32, ttl: Option<Duration>) -> Result<(), ()> {
+            self.map.borrow_mut().insert(*key, (*value, ttl));
+            Ok(())
+        }
+
+        fn delete(&self, key: &i32) -> Result<(), ()> {
+            self.map.borrow_mut().remove(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_store_cache_behind_lru() {
+        let ttl = Duration::from_secs(30);
+        let mut cache = LruCache::new(1).chain(StoreCache::new(MapStore::default()).with_ttl(ttl));
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.second().store().map.borrow().get(&1), Some(&(1, Some(ttl))));
+        assert_eq!(cache.get(&1), Some(1));
+        assert_eq!(cache.get(&2), Some(2));
+        cache.delete(&2);
+        assert_eq!(cache.get(&2), None);
+    }
+}
@@ -0,0 +1,69 @@
+// proptest strategies for code that embeds the cache
+use std::fmt;
+use std::hash::Hash;
+use std::time::Duration;
+
+use proptest::arbitrary::{Arbitrary, any};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::strategy::BoxedStrategy;
+
+use crate::replay::{Op, replay};
+use crate::{EntryOptions, LruCache};
+
+// a single operation over keys and values from the given strategies.
+// ttls are kept long so generated sequences behave the same however fast they run
+pub fn op<K, V>(keys: impl Strategy<Value = K> + Clone, values: impl Strategy<Value = V> + Clone) -> impl Strategy<Value = Op<K, V>>
+where
+    K: fmt::Debug + Clone,
+    V: fmt::Debug + Clone
+{
+    prop_oneof![
+        4 => keys.clone().prop_map(Op::Get),
+        4 => (keys.clone(), values.clone()).prop_map(|(key, value)| Op::Put(key, value)),
+        1 => (keys.clone(), values, 60..3600u64).prop_map(|(key, value, ttl)| {
+            Op::PutWithOptions(key, value, EntryOptions::ttl(Duration::from_secs(ttl)))
+        }),
+        2 => keys.prop_map(Op::Delete),
+        1 => (1..64usize).prop_map(Op::Resize),
+        1 => Just(Op::Reset)
+    ]
+}
+
+// a sequence of up to `len` operations, starting with a capacity
+pub fn ops<K, V>(keys: impl Strategy<Value = K> + Clone, values: impl Strategy<Value = V> + Clone, len: usize) -> impl Strategy<Value = Vec<Op<K, V>>>
+where
+    K: fmt::Debug + Clone,
+    V: fmt::Debug + Clone
+{
+    (1..64usize, vec(op(keys, values), 0..=len)).prop_map(|(capacity, mut ops)| {
+        ops.insert(0, Op::Resize(capacity));
+        ops
+    })
+}
+
+// a cache in the state left by a random capacity and operation sequence
+impl<K, V> Arbitrary for LruCache<K, V>
+where
+    K: Arbitrary + Clone + Eq + Hash + fmt::Debug + 'static,
+    V: Arbitrary + Clone + fmt::Debug + 'static
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        ops(any::<K>().boxed(), any::<V>().boxed(), 64).prop_map(|ops| replay(&ops)).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_arbitrary_cache_within_capacity(cache in any::<LruCache<u8, u8>>()) {
+            prop_assert!(cache.len() <= cache.capacity());
+        }
+    }
+}
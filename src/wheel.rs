@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const SLOTS: usize = 64;
+const SLOT_BITS: u32 = 6;
+const LEVELS: usize = 4;
+
+// hierarchical timer wheel: O(1) scheduling, deadlines are rounded up to whole ticks
+pub(crate) struct TimerWheel<K> {
+    start: Instant,
+    tick: Duration,
+    current: u64,
+    levels: Vec<Vec<Vec<(u64, u64)>>>,
+    overflow: Vec<(u64, u64)>,
+    ready: Vec<u64>,
+    keys: HashMap<u64, K>,
+    next_id: u64
+}
+
+impl<K> TimerWheel<K> {
+    pub(crate) fn new(tick: Duration) -> Self {
+        TimerWheel {
+            start: Instant::now(),
+            tick: tick.max(Duration::from_nanos(1)),
+            current: 0,
+            levels: (0..LEVELS).map(|_| (0..SLOTS).map(|_| Vec::new()).collect()).collect(),
+            overflow: Vec::new(),
+            ready: Vec::new(),
+            keys: HashMap::new(),
+            next_id: 0
+        }
+    }
+
+    pub(crate) fn schedule(&mut self, key: K, deadline: Instant) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.keys.insert(id, key);
+
+        let nanos = deadline.saturating_duration_since(self.start).as_nanos();
+        let tick = nanos.div_ceil(self.tick.as_nanos()) as u64;
+        self.place(tick, id);
+        id
+    }
+
+    pub(crate) fn cancel(&mut self, id: u64) {
+        self.keys.remove(&id);
+    }
+
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<K> {
+        let target = (now.saturating_duration_since(self.start).as_nanos() / self.tick.as_nanos()) as u64;
+        self.advance(target);
+        while let Some(id) = self.ready.pop() {
+            if let Some(key) = self.keys.remove(&id) {
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for level in &mut self.levels {
+            for slot in level {
+                slot.clear();
+            }
+        }
+        self.overflow.clear();
+        self.ready.clear();
+        self.keys.clear();
+    }
+
+    fn place(&mut self, tick: u64, id: u64) {
+        if tick <= self.current {
+            self.ready.push(id);
+            return;
+        }
+
+        let delta = tick - self.current;
+        for level in 0..LEVELS {
+            if delta < 1 << (SLOT_BITS * (level as u32 + 1)) {
+                let slot = (tick >> (SLOT_BITS * level as u32)) as usize & (SLOTS - 1);
+                self.levels[level][slot].push((tick, id));
+                return;
+            }
+        }
+        self.overflow.push((tick, id));
+    }
+
+    fn advance(&mut self, target: u64) {
+        if self.keys.is_empty() {
+            self.current = self.current.max(target);
+            return;
+        }
+
+        // past a whole wheel span every slot would be visited anyway, so re-place everything against the target
+        // instead of walking the gap one tick at a time
+        if target.saturating_sub(self.current) >= 1 << (SLOT_BITS * LEVELS as u32) {
+            let mut pending = std::mem::take(&mut self.overflow);
+            for level in &mut self.levels {
+                for slot in level {
+                    pending.append(slot);
+                }
+            }
+            self.current = target;
+            for (tick, id) in pending {
+                self.place(tick, id);
+            }
+            return;
+        }
+
+        while self.current < target {
+            self.current += 1;
+            let now = self.current;
+
+            if now.trailing_zeros() >= SLOT_BITS * (LEVELS as u32 - 1) {
+                for (tick, id) in std::mem::take(&mut self.overflow) {
+                    self.place(tick, id);
+                }
+            }
+            for level in (1..LEVELS).rev() {
+                let shift = SLOT_BITS * level as u32;
+                if now & ((1 << shift) - 1) == 0 {
+                    let slot = (now >> shift) as usize & (SLOTS - 1);
+                    for (tick, id) in std::mem::take(&mut self.levels[level][slot]) {
+                        self.place(tick, id);
+                    }
+                }
+            }
+
+            let slot = now as usize & (SLOTS - 1);
+            for (_, id) in std::mem::take(&mut self.levels[0][slot]) {
+                self.ready.push(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_wheel_fires_across_levels() {
+        let mut wheel = TimerWheel::new(Duration::from_millis(1));
+        let start = wheel.start;
+        wheel.schedule("near", start + Duration::from_millis(3));
+        wheel.schedule("far", start + Duration::from_millis(5000));
+        let cancelled = wheel.schedule("cancelled", start + Duration::from_millis(10));
+        wheel.cancel(cancelled);
+
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(2)), None);
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(3)), Some("near"));
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(4999)), None);
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(5000)), Some("far"));
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_timer_wheel_jumps_long_gaps() {
+        // a nanosecond tick makes a ten second gap billions of ticks, far too many to walk
+        let mut wheel = TimerWheel::new(Duration::from_nanos(1));
+        let start = wheel.start;
+        wheel.schedule("soon", start + Duration::from_secs(1));
+        wheel.schedule("later", start + Duration::from_secs(20));
+        wheel.schedule("after", start + Duration::from_secs(20) + Duration::from_nanos(100));
+
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(10)), Some("soon"));
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(10)), None);
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(20) - Duration::from_nanos(1)), None);
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(20)), Some("later"));
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(20) + Duration::from_nanos(99)), None);
+        assert_eq!(wheel.pop_due(start + Duration::from_secs(20) + Duration::from_nanos(100)), Some("after"));
+    }
+}
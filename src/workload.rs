@@ -0,0 +1,114 @@
+// seeded key streams for benchmarks and simulations. the same seed gives the same stream on every machine and
+// every commit, so measurements of one workload are comparable over time
+
+// splitmix64, good enough to drive workloads and needs no dependency
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// keys 0..n, each equally likely
+pub fn uniform(n: u64, seed: u64) -> impl Iterator<Item = u64> {
+    let mut rng = Rng::new(seed);
+    std::iter::repeat_with(move || rng.next_u64() % n.max(1))
+}
+
+// 0, 1, .. n - 1 over and over, the access pattern that flushes a plain lru smaller than n
+pub fn scan(n: u64) -> impl Iterator<Item = u64> {
+    (0..n.max(1)).cycle()
+}
+
+// keys 0..n where key k is drawn with probability proportional to 1 / (k + 1)^exponent, so 0 is the hottest.
+// an exponent around 1 is typical of web and database traffic, higher is more skewed
+#[derive(Clone, Debug)]
+pub struct Zipfian {
+    // cumulative probabilities, the last one is 1
+    cdf: Vec<f64>,
+    rng: Rng
+}
+
+impl Zipfian {
+    // builds a table of n entries, so n is bounded by memory rather than meant to be huge
+    pub fn new(n: u64, exponent: f64, seed: u64) -> Self {
+        let weights: Vec<f64> = (1..=n.max(1)).map(|rank| 1.0 / (rank as f64).powf(exponent)).collect();
+        let total: f64 = weights.iter().sum();
+        let mut sum = 0.0;
+        let cdf = weights.iter().map(|weight| {
+            sum += weight / total;
+            sum
+        }).collect();
+        Zipfian { cdf, rng: Rng::new(seed) }
+    }
+}
+
+impl Iterator for Zipfian {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let draw = self.rng.next_f64();
+        let key = self.cdf.partition_point(|&cumulative| cumulative <= draw);
+        Some(key.min(self.cdf.len() - 1) as u64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Get(u64),
+    Put(u64)
+}
+
+// reads `read_ratio` of the time and writes otherwise, each on the next key from `keys`
+pub fn mixed<I: Iterator<Item = u64>>(keys: I, read_ratio: f64, seed: u64) -> impl Iterator<Item = Op> {
+    let mut rng = Rng::new(seed);
+    keys.map(move |key| if rng.next_f64() < read_ratio { Op::Get(key) } else { Op::Put(key) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workloads_are_deterministic() {
+        let first: Vec<u64> = uniform(100, 7).take(50).collect();
+        assert_eq!(first, uniform(100, 7).take(50).collect::<Vec<_>>());
+        assert_ne!(first, uniform(100, 8).take(50).collect::<Vec<_>>());
+        assert!(first.iter().all(|&key| key < 100));
+        assert_eq!(scan(3).take(5).collect::<Vec<_>>(), vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_zipfian_is_skewed() {
+        let keys: Vec<u64> = Zipfian::new(1000, 1.0, 1).take(10_000).collect();
+        assert!(keys.iter().all(|&key| key < 1000));
+        let hottest = keys.iter().filter(|&&key| key == 0).count();
+        let coldest_half = keys.iter().filter(|&&key| key >= 500).count();
+        // about 13% of draws go to key 0 and under 10% to the 500 coldest keys
+        assert!(hottest > 1000, "{} draws of 0", hottest);
+        assert!(coldest_half < 1200, "{} draws of the coldest half", coldest_half);
+    }
+
+    #[test]
+    fn test_mixed_read_ratio() {
+        let reads = mixed(scan(10), 0.9, 3).take(1000).filter(|op| matches!(op, Op::Get(_))).count();
+        assert!((850..950).contains(&reads), "{} reads", reads);
+    }
+}